@@ -10,6 +10,35 @@ fn day1(c: &mut Criterion) {
     c.bench_function("day 1 part 2", |b| {
         b.iter(|| aoc_2023::day01::day1_part2(black_box(&text)))
     });
+    c.bench_function("day 1 part 2 (automaton)", |b| {
+        b.iter(|| aoc_2023::day01::day1_part2_automaton(black_box(&text)))
+    });
+
+    let text = long_lines();
+    c.bench_function("day 1 part 2 long lines", |b| {
+        b.iter(|| aoc_2023::day01::day1_part2(black_box(&text)))
+    });
+    c.bench_function("day 1 part 2 long lines (automaton)", |b| {
+        b.iter(|| aoc_2023::day01::day1_part2_automaton(black_box(&text)))
+    });
+}
+
+/// Lines of several kilobytes, where the only numbers are near the middle of the line, full of
+/// near-misses like "thre" and "nin" that the matcher has to back out of.
+fn long_lines() -> Vec<u8> {
+    const FILLER: &[u8] = b"thrninsevfoeigxtwsinfivzonthre";
+    let mut text = Vec::new();
+    for line in 0..100 {
+        for i in 0..4000 {
+            text.push(FILLER[(i + line) % FILLER.len()]);
+        }
+        text.extend_from_slice(b"seven3oneight");
+        for i in 0..4000 {
+            text.push(FILLER[(i + line) % FILLER.len()]);
+        }
+        text.push(b'\n');
+    }
+    text
 }
 
 criterion_group! {
@@ -7,6 +7,9 @@ fn day3(c: &mut Criterion) {
     c.bench_function("day 3 part 1", |b| {
         b.iter(|| aoc_2023::day03::day3_part1(black_box(&text)))
     });
+    c.bench_function("day 3 part 1 (single pass)", |b| {
+        b.iter(|| aoc_2023::day03::day3_part1_single_pass(black_box(&text)))
+    });
     c.bench_function("day 3 part 2", |b| {
         b.iter(|| aoc_2023::day03::day3_part2(black_box(&text)))
     });
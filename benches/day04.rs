@@ -10,6 +10,12 @@ fn day4(c: &mut Criterion) {
     c.bench_function("day 4 part 2", |b| {
         b.iter(|| aoc_2023::day04::day4_part2(black_box(&text)))
     });
+    c.bench_function("day 4 part 1 (bitset)", |b| {
+        b.iter(|| aoc_2023::day04::day4_part1_bitset(black_box(&text)))
+    });
+    c.bench_function("day 4 part 2 (bitset)", |b| {
+        b.iter(|| aoc_2023::day04::day4_part2_bitset(black_box(&text)))
+    });
 }
 
 criterion_group! {
@@ -1,5 +1,6 @@
 use std::time::Duration;
 
+use aoc_2023::utils::XorShift;
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
 fn day5(c: &mut Criterion) {
@@ -7,9 +8,68 @@ fn day5(c: &mut Criterion) {
     c.bench_function("day 5 part 1", |b| {
         b.iter(|| aoc_2023::day05::day5_part1(black_box(&text)))
     });
+    c.bench_function("day 5 part 1 (sorted)", |b| {
+        b.iter(|| aoc_2023::day05::day5_part1_sorted(black_box(&text)))
+    });
+    let generated = generated_almanac(4000, 2000);
+    c.bench_function("day 5 part 1 generated", |b| {
+        b.iter(|| aoc_2023::day05::day5_part1(black_box(&generated)))
+    });
     c.bench_function("day 5 part 2", |b| {
         b.iter(|| aoc_2023::day05::day5_part2(black_box(&text)))
     });
+    c.bench_function("day 5 part 2 (unfused)", |b| {
+        b.iter(|| aoc_2023::day05::day5_part2_unfused(black_box(&text)))
+    });
+    c.bench_function("day 5 part 1 (composed)", |b| {
+        b.iter(|| aoc_2023::day05::day5_part1_composed(black_box(&text)))
+    });
+    c.bench_function("day 5 part 2 (composed)", |b| {
+        b.iter(|| aoc_2023::day05::day5_part2_composed(black_box(&text)))
+    });
+}
+
+/// An almanac with `seeds` seeds and seven stages of `lines` map lines each. Every stage cuts
+/// the values below ten billion into shuffled pieces, so no two lines overlap.
+fn generated_almanac(seeds: usize, lines: usize) -> Vec<u8> {
+    const CATEGORIES: [&str; 8] = [
+        "seed",
+        "soil",
+        "fertilizer",
+        "water",
+        "light",
+        "temperature",
+        "humidity",
+        "location",
+    ];
+    const LIMIT: u64 = 10_000_000_000;
+    let mut rng = XorShift::new(0x2545_f491_4f6c_dd1d);
+
+    let mut text = String::from("seeds:");
+    for _ in 0..seeds {
+        text += &format!(" {}", rng.below(LIMIT));
+    }
+    text += "\n";
+    for stage in CATEGORIES.windows(2) {
+        text += &format!("\n{}-to-{} map:\n", stage[0], stage[1]);
+        let mut cuts = (1..lines).map(|_| rng.below(LIMIT)).collect::<Vec<_>>();
+        cuts.extend([0, LIMIT]);
+        cuts.sort_unstable();
+        cuts.dedup();
+        let mut pieces = cuts
+            .windows(2)
+            .map(|cut| (cut[0], cut[1] - cut[0]))
+            .collect::<Vec<_>>();
+        for i in (1..pieces.len()).rev() {
+            pieces.swap(i, rng.below(i as u64 + 1) as usize);
+        }
+        let mut destination = 0;
+        for (source, size) in pieces {
+            text += &format!("{destination} {source} {size}\n");
+            destination += size;
+        }
+    }
+    text.into_bytes()
 }
 
 criterion_group! {
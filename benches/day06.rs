@@ -10,6 +10,12 @@ fn day6(c: &mut Criterion) {
     c.bench_function("day 6 part 2", |b| {
         b.iter(|| aoc_2023::day06::day6_part2(black_box(&text)))
     });
+    c.bench_function("day 6 part 1 (fast)", |b| {
+        b.iter(|| aoc_2023::day06::day6_part1_fast(black_box(&text)))
+    });
+    c.bench_function("day 6 part 2 (fast)", |b| {
+        b.iter(|| aoc_2023::day06::day6_part2_fast(black_box(&text)))
+    });
 }
 
 criterion_group! {
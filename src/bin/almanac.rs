@@ -0,0 +1,35 @@
+//! Checks day 5 almanacs for overlapping, colliding or gapped map lines.
+//!
+//! Usage: `cargo run --bin almanac [file...]`, defaulting to the real day 5 input.
+
+use std::{env, fs, process::ExitCode};
+
+fn main() -> ExitCode {
+    let mut paths = env::args().skip(1).collect::<Vec<_>>();
+    if paths.is_empty() {
+        paths.push("inputs/day5.txt".to_owned());
+    }
+
+    let mut failed = false;
+    for path in paths {
+        let input = match fs::read(&path) {
+            Ok(input) => input,
+            Err(error) => {
+                eprintln!("{path}: {error}");
+                failed = true;
+                continue;
+            }
+        };
+        for issue in aoc_2023::day05::validate(&input) {
+            let level = if issue.is_error() { "error" } else { "note" };
+            println!("{path}:{issue} ({level})");
+            failed |= issue.is_error();
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
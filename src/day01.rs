@@ -1,3 +1,7 @@
+use std::cmp::Reverse;
+
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
 pub fn day1_part1(input: &[u8]) -> u32 {
     let mut start = 0;
     let mut sum = 0;
@@ -27,6 +31,472 @@ pub fn day1_part2(input: &[u8]) -> u32 {
     sum
 }
 
+/// Inputs smaller than this are solved on the current thread, since splitting them up costs more
+/// than it saves.
+const PARALLEL_THRESHOLD: usize = 4 << 20;
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// Splits large inputs into chunks of whole lines and solves them in parallel. The sum is returned
+/// as a `u64`, as very large inputs can overflow a `u32`.
+pub fn day1_part1_parallel(input: &[u8]) -> u64 {
+    solve_chunked(input, CHUNK_SIZE, day1_part1)
+}
+
+pub fn day1_part2_parallel(input: &[u8]) -> u64 {
+    solve_chunked(input, CHUNK_SIZE, day1_part2_automaton)
+}
+
+fn solve_chunked(input: &[u8], chunk_size: usize, solve: impl Fn(&[u8]) -> u32 + Sync) -> u64 {
+    if input.len() < PARALLEL_THRESHOLD {
+        return solve(input).into();
+    }
+
+    line_chunks(input, chunk_size)
+        .collect::<Vec<_>>()
+        .par_iter()
+        .map(|chunk| u64::from(solve(chunk)))
+        .sum()
+}
+
+/// Splits the input into chunks of roughly `chunk_size` bytes, each ending with a newline (apart
+/// from possibly the last one).
+fn line_chunks(input: &[u8], chunk_size: usize) -> impl Iterator<Item = &[u8]> {
+    let mut start = 0;
+    std::iter::from_fn(move || {
+        if start >= input.len() {
+            return None;
+        }
+        let end = match input.get(start + chunk_size..) {
+            Some(rest) => {
+                memchr::memchr(b'\n', rest).map_or(input.len(), |nl| start + chunk_size + nl + 1)
+            }
+            None => input.len(),
+        };
+        let chunk = &input[start..end];
+        start = end;
+        Some(chunk)
+    })
+}
+
+pub fn day1_part2_automaton(input: &[u8]) -> u32 {
+    let mut start = 0;
+    let mut sum = 0;
+    for end in memchr::memchr_iter(b'\n', input) {
+        sum += calibration_value(
+            &input[start..end],
+            |line| FORWARDS.first_digit(line),
+            |line| BACKWARDS.last_digit(line),
+        );
+        start = end;
+    }
+    sum
+}
+
+pub fn day1_part2_with_vocabulary(input: &[u8], vocabulary: &Vocabulary) -> u32 {
+    let mut start = 0;
+    let mut sum = 0;
+    for end in memchr::memchr_iter(b'\n', input) {
+        sum += calibration_value(
+            &input[start..end],
+            |line| vocabulary.first_digit(line),
+            |line| vocabulary.last_digit(line),
+        );
+        start = end;
+    }
+    sum
+}
+
+const WORDS: [&[u8]; 9] = [
+    b"one", b"two", b"three", b"four", b"five", b"six", b"seven", b"eight", b"nine",
+];
+
+const MAX_STATES: usize = 64;
+const MAX_CLASSES: usize = 16;
+
+static FORWARDS: Automaton = Automaton::new(&WORDS, false);
+static BACKWARDS: Automaton = Automaton::new(&WORDS, true);
+
+/// An Aho-Corasick automaton over the spelled-out digits, built at compile time. Every byte of
+/// the line is looked at once, and the automaton reports a word as soon as its last byte has been
+/// read. The backwards automaton is built from the reversed words, and is fed the line from its
+/// end.
+///
+/// Reporting the first word to *end* only finds the first word to *start* because none of the
+/// words contain another word, so this can't be used for arbitrary vocabularies.
+struct Automaton {
+    /// Bytes that appear in any word get their own class; everything else is class 0.
+    classes: [u8; 256],
+    transitions: [[u8; MAX_CLASSES]; MAX_STATES],
+    /// The value of the word that has just been matched in each state, or 0 if there isn't one.
+    outputs: [u8; MAX_STATES],
+}
+
+impl Automaton {
+    const fn new(words: &[&[u8]], reversed: bool) -> Self {
+        let mut classes = [0_u8; 256];
+        let mut class_count = 1;
+        // The trie of all words, where a transition to state 0 (the root) means there is no child.
+        let mut trie = [[0_u8; MAX_CLASSES]; MAX_STATES];
+        let mut outputs = [0_u8; MAX_STATES];
+        let mut state_count = 1;
+
+        let mut w = 0;
+        while w < words.len() {
+            let word = words[w];
+            let mut state = 0;
+            let mut i = 0;
+            while i < word.len() {
+                let byte = if reversed {
+                    word[word.len() - 1 - i]
+                } else {
+                    word[i]
+                };
+                if classes[byte as usize] == 0 {
+                    assert!(class_count < MAX_CLASSES, "too many distinct bytes");
+                    classes[byte as usize] = class_count as u8;
+                    class_count += 1;
+                }
+                let class = classes[byte as usize] as usize;
+                if trie[state][class] == 0 {
+                    assert!(state_count < MAX_STATES, "too many states");
+                    trie[state][class] = state_count as u8;
+                    state_count += 1;
+                }
+                state = trie[state][class] as usize;
+                i += 1;
+            }
+            outputs[state] = (w + 1) as u8;
+            w += 1;
+        }
+
+        // Breadth-first over the trie, so that each state's failure state (which is always
+        // shallower) has been completed before the state itself.
+        let mut transitions = [[0_u8; MAX_CLASSES]; MAX_STATES];
+        let mut failures = [0_u8; MAX_STATES];
+        let mut queue = [0_u8; MAX_STATES];
+        let mut queue_end = 1;
+        let mut queue_start = 0;
+        while queue_start < queue_end {
+            let state = queue[queue_start] as usize;
+            queue_start += 1;
+            let failure = failures[state] as usize;
+            let mut class = 0;
+            while class < class_count {
+                let child = trie[state][class];
+                if child == 0 {
+                    transitions[state][class] = transitions[failure][class];
+                } else {
+                    let child_failure = if state == 0 {
+                        0
+                    } else {
+                        transitions[failure][class]
+                    };
+                    failures[child as usize] = child_failure;
+                    if outputs[child as usize] == 0 {
+                        outputs[child as usize] = outputs[child_failure as usize];
+                    }
+                    transitions[state][class] = child;
+                    queue[queue_end] = child;
+                    queue_end += 1;
+                }
+                class += 1;
+            }
+        }
+
+        Self {
+            classes,
+            transitions,
+            outputs,
+        }
+    }
+
+    #[inline(always)]
+    fn step(&self, state: u8, byte: u8) -> u8 {
+        self.transitions[state as usize][self.classes[byte as usize] as usize]
+    }
+
+    #[inline(always)]
+    fn first_digit(&self, line: &[u8]) -> u8 {
+        let mut pos = 0;
+        let mut state = 0;
+        loop {
+            if let c @ b'1'..=b'9' = line[pos] {
+                return c - b'0';
+            }
+            state = self.step(state, line[pos]);
+            if self.outputs[state as usize] != 0 {
+                return self.outputs[state as usize];
+            }
+            pos += 1;
+        }
+    }
+
+    #[inline(always)]
+    fn last_digit(&self, line: &[u8]) -> u8 {
+        let mut pos = line.len();
+        let mut state = 0;
+        loop {
+            pos -= 1;
+            if let c @ b'1'..=b'9' = line[pos] {
+                return c - b'0';
+            }
+            state = self.step(state, line[pos]);
+            if self.outputs[state as usize] != 0 {
+                return self.outputs[state as usize];
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Word {
+    text: Box<[u8]>,
+    first_digit: u8,
+    last_digit: u8,
+}
+
+/// A set of words that are recognised as numbers when reading calibration values, in addition
+/// to the digits `1` to `9`.
+///
+/// Words may stand for numbers with more than one digit (e.g. "ten"), in which case the first
+/// digit of the number is used when the word starts a line, and the last digit when it ends one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vocabulary {
+    // Words are stored twice, grouped by their first and by their last byte, so that only the
+    // words that could possibly match at a given position need to be compared.
+    forwards: Vec<Word>,
+    forwards_index: [u32; 257],
+    backwards: Vec<Word>,
+    backwards_index: [u32; 257],
+}
+
+impl Vocabulary {
+    pub fn new<'a>(words: impl IntoIterator<Item = (&'a str, u32)>) -> Self {
+        let mut forwards = words
+            .into_iter()
+            .filter(|(text, _)| !text.is_empty())
+            .map(|(text, value)| Word {
+                text: text.as_bytes().into(),
+                first_digit: leading_digit(value),
+                last_digit: (value % 10) as u8,
+            })
+            .collect::<Vec<_>>();
+        let mut backwards = forwards.clone();
+        // Longer words come first within each group, so that the longest word matching at a
+        // position wins, whatever order the words were given in.
+        forwards.sort_by_key(|word| (word.text[0], Reverse(word.text.len())));
+        backwards.sort_by_key(|word| (word.text[word.text.len() - 1], Reverse(word.text.len())));
+        let forwards_index = byte_index(&forwards, |word| word.text[0]);
+        let backwards_index = byte_index(&backwards, |word| word.text[word.text.len() - 1]);
+        Self {
+            forwards,
+            forwards_index,
+            backwards,
+            backwards_index,
+        }
+    }
+
+    pub fn english() -> Self {
+        Self::new([
+            ("one", 1),
+            ("two", 2),
+            ("three", 3),
+            ("four", 4),
+            ("five", 5),
+            ("six", 6),
+            ("seven", 7),
+            ("eight", 8),
+            ("nine", 9),
+        ])
+    }
+
+    pub fn german() -> Self {
+        Self::new([
+            ("eins", 1),
+            ("zwei", 2),
+            ("drei", 3),
+            ("vier", 4),
+            ("fünf", 5),
+            ("sechs", 6),
+            ("sieben", 7),
+            ("acht", 8),
+            ("neun", 9),
+        ])
+    }
+
+    pub fn french() -> Self {
+        Self::new([
+            ("un", 1),
+            ("deux", 2),
+            ("trois", 3),
+            ("quatre", 4),
+            ("cinq", 5),
+            ("six", 6),
+            ("sept", 7),
+            ("huit", 8),
+            ("neuf", 9),
+        ])
+    }
+
+    fn starting_with(&self, byte: u8) -> &[Word] {
+        let index = usize::from(byte);
+        &self.forwards[self.forwards_index[index] as usize..self.forwards_index[index + 1] as usize]
+    }
+
+    fn ending_with(&self, byte: u8) -> &[Word] {
+        let index = usize::from(byte);
+        &self.backwards
+            [self.backwards_index[index] as usize..self.backwards_index[index + 1] as usize]
+    }
+
+    /// A vocabulary without any words, so that only the digits `1` to `9` are recognised.
+    pub fn digits() -> Self {
+        Self::new([])
+    }
+
+    fn first_digit(&self, line: &[u8]) -> u8 {
+        self.find_first(line).unwrap().digit
+    }
+
+    fn last_digit(&self, line: &[u8]) -> u8 {
+        self.find_last(line).unwrap().digit
+    }
+
+    fn find_first(&self, line: &[u8]) -> Option<DigitMatch> {
+        for (offset, &byte) in line.iter().enumerate() {
+            if let b'1'..=b'9' = byte {
+                return Some(DigitMatch {
+                    digit: byte - b'0',
+                    offset,
+                    source: DigitSource::Digit,
+                });
+            }
+            for word in self.starting_with(byte) {
+                if line[offset..].starts_with(&word.text) {
+                    return Some(DigitMatch {
+                        digit: word.first_digit,
+                        offset,
+                        source: DigitSource::Word,
+                    });
+                }
+            }
+        }
+        None
+    }
+
+    fn find_last(&self, line: &[u8]) -> Option<DigitMatch> {
+        for (offset, &byte) in line.iter().enumerate().rev() {
+            if let b'1'..=b'9' = byte {
+                return Some(DigitMatch {
+                    digit: byte - b'0',
+                    offset,
+                    source: DigitSource::Digit,
+                });
+            }
+            for word in self.ending_with(byte) {
+                if line[..=offset].ends_with(&word.text) {
+                    return Some(DigitMatch {
+                        digit: word.last_digit,
+                        offset: offset + 1 - word.text.len(),
+                        source: DigitSource::Word,
+                    });
+                }
+            }
+        }
+        None
+    }
+}
+
+impl Default for Vocabulary {
+    fn default() -> Self {
+        Self::english()
+    }
+}
+
+fn leading_digit(mut value: u32) -> u8 {
+    while value >= 10 {
+        value /= 10;
+    }
+    value as u8
+}
+
+/// Builds an index where the words with the key byte `b` are at `index[b]..index[b + 1]`. The
+/// words must already be sorted by key.
+fn byte_index(words: &[Word], key: impl Fn(&Word) -> u8) -> [u32; 257] {
+    let mut index = [0; 257];
+    for word in words {
+        index[usize::from(key(word)) + 1] += 1;
+    }
+    for i in 1..index.len() {
+        index[i] += index[i - 1];
+    }
+    index
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigitSource {
+    Digit,
+    Word,
+}
+
+/// A digit found in a line, with the byte offset where its digit or word starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DigitMatch {
+    pub digit: u8,
+    pub offset: usize,
+    pub source: DigitSource,
+}
+
+/// How the calibration value for a single line was worked out. Line numbers start at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineCalibration<'a> {
+    pub line_number: usize,
+    pub text: &'a [u8],
+    pub first: DigitMatch,
+    pub last: DigitMatch,
+    pub value: u32,
+}
+
+/// A line that has no digit in it, and therefore no calibration value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingDigit<'a> {
+    pub line_number: usize,
+    pub text: &'a [u8],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CalibrationReport<'a> {
+    pub lines: Vec<LineCalibration<'a>>,
+    pub missing: Vec<MissingDigit<'a>>,
+}
+
+impl CalibrationReport<'_> {
+    pub fn total(&self) -> u32 {
+        self.lines.iter().map(|line| line.value).sum()
+    }
+}
+
+/// Breaks the calibration document down line by line. Use [`Vocabulary::digits`] to get the
+/// part 1 values, or [`Vocabulary::english`] for part 2. Unlike `day1_part1` and `day1_part2`,
+/// lines without any digits are reported rather than causing a panic.
+pub fn calibration_report<'a>(input: &'a [u8], vocabulary: &Vocabulary) -> CalibrationReport<'a> {
+    let input = input.strip_suffix(b"\n").unwrap_or(input);
+    let mut report = CalibrationReport::default();
+    for (text, line_number) in input.split(|&byte| byte == b'\n').zip(1..) {
+        match (vocabulary.find_first(text), vocabulary.find_last(text)) {
+            (Some(first), Some(last)) => report.lines.push(LineCalibration {
+                line_number,
+                text,
+                first,
+                last,
+                value: (first.digit * 10 + last.digit).into(),
+            }),
+            _ => report.missing.push(MissingDigit { line_number, text }),
+        }
+    }
+    report
+}
+
 #[inline(always)]
 fn calibration_value(
     line: &[u8],
@@ -236,6 +706,264 @@ pub mod tests {
         );
     }
 
+    #[test]
+    fn english_vocabulary_matches_hard_coded_words() {
+        let english = Vocabulary::english();
+        for line in [
+            &b"two1nine"[..],
+            b"rkonedbbf9hq",
+            b"1",
+            b"one",
+            b"nine9",
+            b"oneight",
+        ] {
+            assert_eq!(
+                calibration_value(line, parse_words_forwards, parse_words_backwards),
+                calibration_value(
+                    line,
+                    |line| english.first_digit(line),
+                    |line| english.last_digit(line)
+                ),
+            );
+        }
+    }
+
+    #[test]
+    fn automaton_recognises_every_word_in_both_directions() {
+        for (word, value) in WORDS.iter().zip(1..) {
+            assert_eq!(FORWARDS.first_digit(word), value);
+            assert_eq!(BACKWARDS.last_digit(word), value);
+        }
+    }
+
+    #[test]
+    fn automaton_handles_overlapping_words() {
+        assert_eq!(FORWARDS.first_digit(b"oneight"), 1);
+        assert_eq!(BACKWARDS.last_digit(b"oneight"), 8);
+        assert_eq!(FORWARDS.first_digit(b"twone"), 2);
+        assert_eq!(BACKWARDS.last_digit(b"twone"), 1);
+        assert_eq!(FORWARDS.first_digit(b"eightwothree"), 8);
+        assert_eq!(BACKWARDS.last_digit(b"sevenine"), 9);
+        assert_eq!(FORWARDS.first_digit(b"ninine"), 9);
+        assert_eq!(FORWARDS.first_digit(b"thrthree"), 3);
+        assert_eq!(BACKWARDS.last_digit(b"fivefiv"), 5);
+        assert_eq!(FORWARDS.first_digit(b"sevseven"), 7);
+        assert_eq!(FORWARDS.first_digit(b"xfonf2"), 2);
+    }
+
+    #[test]
+    fn automaton_matches_starts_with_implementation() {
+        let lines: [&[u8]; 8] = [
+            b"two1nine",
+            b"eightwothree",
+            b"abcone2threexyz",
+            b"xtwone3four",
+            b"4nineeightseven2",
+            b"zoneight234",
+            b"7pqrstsixteen",
+            b"\nrkonedbbf9hq",
+        ];
+        for line in lines {
+            assert_eq!(FORWARDS.first_digit(line), parse_words_forwards(line));
+            assert_eq!(BACKWARDS.last_digit(line), parse_words_backwards(line));
+        }
+    }
+
+    #[test]
+    fn test_day1_part2_automaton_real() {
+        let input = utils::load_real(1);
+        assert_eq!(day1_part2_automaton(&input), 54676);
+    }
+
+    #[test]
+    fn german_and_french_vocabularies_recognise_their_words() {
+        let german = Vocabulary::german();
+        assert_eq!(day1_part2_with_vocabulary(b"zweiundvierzig\n", &german), 24);
+        assert_eq!(
+            day1_part2_with_vocabulary("xfünf3achtx\n".as_bytes(), &german),
+            58
+        );
+        assert_eq!(day1_part2_with_vocabulary(b"seinsx\n", &german), 11);
+
+        let french = Vocabulary::french();
+        assert_eq!(day1_part2_with_vocabulary(b"troisdeuxneuf\n", &french), 39);
+        assert_eq!(
+            day1_part2_with_vocabulary(b"quatre7septhuit\n", &french),
+            48
+        );
+        assert_eq!(day1_part2_with_vocabulary(b"one2three\n", &french), 22);
+    }
+
+    #[test]
+    fn longest_word_wins_regardless_of_order() {
+        for vocabulary in [
+            Vocabulary::new([("six", 6), ("sixteen", 16)]),
+            Vocabulary::new([("sixteen", 16), ("six", 6)]),
+        ] {
+            assert_eq!(day1_part2_with_vocabulary(b"sixteen\n", &vocabulary), 16);
+            assert_eq!(day1_part2_with_vocabulary(b"sixteex\n", &vocabulary), 66);
+        }
+        for vocabulary in [
+            Vocabulary::new([("teen", 13), ("nineteen", 19)]),
+            Vocabulary::new([("nineteen", 19), ("teen", 13)]),
+        ] {
+            assert_eq!(day1_part2_with_vocabulary(b"4nineteen\n", &vocabulary), 49);
+        }
+    }
+
+    #[test]
+    fn custom_vocabularies_support_multi_digit_tokens() {
+        let vocabulary = Vocabulary::new([("one", 1), ("ten", 10), ("twelve", 12)]);
+        assert_eq!(day1_part2_with_vocabulary(b"tenxx3\n", &vocabulary), 13);
+        assert_eq!(day1_part2_with_vocabulary(b"4ten\n", &vocabulary), 40);
+        assert_eq!(day1_part2_with_vocabulary(b"twelve\n", &vocabulary), 12);
+        assert_eq!(day1_part2_with_vocabulary(b"onetwelve\n", &vocabulary), 12);
+        assert_eq!(day1_part2_with_vocabulary(b"tenone\n", &vocabulary), 11);
+    }
+
+    #[test]
+    fn test_day1_part2_with_vocabulary_real() {
+        let input = utils::load_real(1);
+        assert_eq!(
+            day1_part2_with_vocabulary(&input, &Vocabulary::english()),
+            54676
+        );
+    }
+
+    #[test]
+    fn calibration_report_describes_each_line() {
+        let report = calibration_report(b"two1nine\nabc\n7pqr\n", &Vocabulary::english());
+        assert_eq!(
+            report.lines,
+            vec![
+                LineCalibration {
+                    line_number: 1,
+                    text: b"two1nine",
+                    first: DigitMatch {
+                        digit: 2,
+                        offset: 0,
+                        source: DigitSource::Word,
+                    },
+                    last: DigitMatch {
+                        digit: 9,
+                        offset: 4,
+                        source: DigitSource::Word,
+                    },
+                    value: 29,
+                },
+                LineCalibration {
+                    line_number: 3,
+                    text: b"7pqr",
+                    first: DigitMatch {
+                        digit: 7,
+                        offset: 0,
+                        source: DigitSource::Digit,
+                    },
+                    last: DigitMatch {
+                        digit: 7,
+                        offset: 0,
+                        source: DigitSource::Digit,
+                    },
+                    value: 77,
+                },
+            ]
+        );
+        assert_eq!(
+            report.missing,
+            vec![MissingDigit {
+                line_number: 2,
+                text: b"abc",
+            }]
+        );
+    }
+
+    #[test]
+    fn calibration_report_uses_vocabulary_for_words() {
+        let input = b"xoneight5\n\nsix\nseven";
+        let report = calibration_report(input, &Vocabulary::digits());
+        assert_eq!(report.total(), 55);
+        assert_eq!(
+            report
+                .missing
+                .iter()
+                .map(|missing| missing.line_number)
+                .collect::<Vec<_>>(),
+            vec![2, 3, 4]
+        );
+
+        let report = calibration_report(input, &Vocabulary::english());
+        assert_eq!(report.total(), 15 + 66 + 77);
+        assert_eq!(report.lines[0].first.offset, 1);
+        assert_eq!(report.lines[0].last.offset, 8);
+        assert_eq!(report.lines[1].last.offset, 0);
+        assert_eq!(report.missing.len(), 1);
+    }
+
+    #[test]
+    fn calibration_report_offsets_point_at_the_start_of_words() {
+        let vocabulary = Vocabulary::new([("ten", 10)]);
+        let report = calibration_report(b"aten1tenb\n", &vocabulary);
+        assert_eq!(report.lines[0].first.offset, 1);
+        assert_eq!(report.lines[0].last.offset, 5);
+        assert_eq!(report.lines[0].value, 10);
+    }
+
+    #[test]
+    fn calibration_report_totals_match_parts() {
+        let input = utils::load_real(1);
+        let report = calibration_report(&input, &Vocabulary::digits());
+        assert_eq!(report.total(), 53921);
+        assert!(report.missing.is_empty());
+        let report = calibration_report(&input, &Vocabulary::english());
+        assert_eq!(report.total(), 54676);
+        assert!(report.missing.is_empty());
+    }
+
+    #[test]
+    fn line_chunks_split_on_newlines() {
+        let input = b"1a\n22b\n333c\n4d\n5";
+        let chunks = line_chunks(input, 4).collect::<Vec<_>>();
+        assert_eq!(chunks, vec![&b"1a\n22b\n"[..], b"333c\n", b"4d\n5"]);
+        let chunks = line_chunks(input, 100).collect::<Vec<_>>();
+        assert_eq!(chunks, vec![&input[..]]);
+        let chunks = line_chunks(b"12\n34\n", 1).collect::<Vec<_>>();
+        assert_eq!(chunks, vec![&b"12\n"[..], b"34\n"]);
+    }
+
+    #[test]
+    fn chunked_solving_matches_sequential_solving() {
+        for (example, part, expected) in [
+            (utils::load_example(1), day1_part1 as fn(&[u8]) -> u32, 142),
+            (utils::load_example_with_suffix(1, "part2"), day1_part2, 281),
+        ] {
+            let copies = PARALLEL_THRESHOLD / example.len() + 1;
+            let input = example.repeat(copies);
+            for chunk_size in [7, 1000, CHUNK_SIZE] {
+                assert_eq!(
+                    solve_chunked(&input, chunk_size, part),
+                    expected * copies as u64
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn chunked_solving_sums_totals_beyond_u32() {
+        let input = b"99\n".repeat(PARALLEL_THRESHOLD / 3 + 1);
+        let chunks = line_chunks(&input, CHUNK_SIZE).count() as u64;
+        assert_eq!(
+            solve_chunked(&input, CHUNK_SIZE, |_| u32::MAX),
+            u64::from(u32::MAX) * chunks
+        );
+    }
+
+    #[test]
+    fn test_day1_parallel_real() {
+        let input = utils::load_real(1);
+        assert_eq!(day1_part1_parallel(&input), 53921);
+        assert_eq!(day1_part2_parallel(&input), 54676);
+    }
+
     #[test]
     fn day1_part1_sums_all_lines() {
         assert_eq!(day1_part1(b"14\n"), 14);
@@ -1,16 +1,44 @@
 use std::hint::unreachable_unchecked;
 
+/// The bag from the puzzle description: 12 red, 13 green, and 14 blue cubes.
+pub const STANDARD_BAG: Draw = Draw {
+    red: 12,
+    green: 13,
+    blue: 14,
+};
+
+// `day2_part1` and `day2_part2` are the fast path: they assume that only red, green, and blue
+// cubes appear, and that no count is larger than 255. Any other colour name reaches
+// `unreachable_unchecked`, and so does passing such an input to `day2_part1_with_bag` or
+// `possible_games`. Use `parse_coloured_games` and the `_any_colours` functions for other inputs.
+
 pub fn day2_part1(input: &[u8]) -> u32 {
+    day2_part1_with_bag(input, STANDARD_BAG)
+}
+
+pub fn day2_part1_with_bag(input: &[u8], bag: Draw) -> u32 {
     let mut index = 0;
     let mut id_sum = 0;
     while index < input.len() {
-        let (id, bytes_read) = id_or_zero(&input[index..]);
-        id_sum += id;
+        let (id, bytes_read) = possible_id(&input[index..], bag);
+        id_sum += id.unwrap_or(0);
         index += bytes_read;
     }
     id_sum
 }
 
+/// Lists the IDs of every game that could have been played with the given bag.
+pub fn possible_games(input: &[u8], bag: Draw) -> Vec<u32> {
+    let mut index = 0;
+    let mut ids = Vec::new();
+    while index < input.len() {
+        let (id, bytes_read) = possible_id(&input[index..], bag);
+        ids.extend(id);
+        index += bytes_read;
+    }
+    ids
+}
+
 pub fn day2_part2(input: &[u8]) -> u32 {
     let mut index = 0;
     let mut power_sum = 0;
@@ -23,6 +51,348 @@ pub fn day2_part2(input: &[u8]) -> u32 {
     power_sum
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Colour {
+    Red,
+    Green,
+    Blue,
+}
+
+impl Colour {
+    fn from_name(name: &[u8]) -> Self {
+        match name {
+            b"red" => Colour::Red,
+            b"green" => Colour::Green,
+            b"blue" => Colour::Blue,
+            _ => panic!("not a colour: {:?}", String::from_utf8_lossy(name)),
+        }
+    }
+}
+
+/// The cubes shown in a single draw. This is also used to describe the contents of a bag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Draw {
+    pub red: u32,
+    pub green: u32,
+    pub blue: u32,
+}
+
+impl Draw {
+    pub fn new(red: u32, green: u32, blue: u32) -> Self {
+        Self { red, green, blue }
+    }
+
+    pub fn get(&self, colour: Colour) -> u32 {
+        match colour {
+            Colour::Red => self.red,
+            Colour::Green => self.green,
+            Colour::Blue => self.blue,
+        }
+    }
+
+    fn get_mut(&mut self, colour: Colour) -> &mut u32 {
+        match colour {
+            Colour::Red => &mut self.red,
+            Colour::Green => &mut self.green,
+            Colour::Blue => &mut self.blue,
+        }
+    }
+
+    /// Whether this draw could have been made from the given bag.
+    pub fn fits_in(&self, bag: Draw) -> bool {
+        self.red <= bag.red && self.green <= bag.green && self.blue <= bag.blue
+    }
+
+    /// The smallest draw containing at least as many cubes of each colour as both draws.
+    pub fn max(self, other: Draw) -> Draw {
+        Draw {
+            red: self.red.max(other.red),
+            green: self.green.max(other.green),
+            blue: self.blue.max(other.blue),
+        }
+    }
+
+    pub fn power(&self) -> u64 {
+        u64::from(self.red) * u64::from(self.green) * u64::from(self.blue)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Game {
+    pub id: u32,
+    pub draws: Vec<Draw>,
+}
+
+impl Game {
+    pub fn is_possible_with(&self, bag: Draw) -> bool {
+        self.draws.iter().all(|draw| draw.fits_in(bag))
+    }
+
+    pub fn minimum_bag(&self) -> Draw {
+        self.draws
+            .iter()
+            .fold(Draw::default(), |bag, &draw| bag.max(draw))
+    }
+
+    pub fn power(&self) -> u64 {
+        self.minimum_bag().power()
+    }
+
+    pub fn max(&self, colour: Colour) -> u32 {
+        self.draws
+            .iter()
+            .map(|draw| draw.get(colour))
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+/// A game that borrows from the input, and parses its draws only when they are iterated over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameRef<'a> {
+    pub id: u32,
+    draws: &'a [u8],
+}
+
+impl<'a> GameRef<'a> {
+    fn parse(line: &'a [u8]) -> Self {
+        let colon = memchr::memchr(b':', line).unwrap();
+        let id = parse_u32(line[5..colon].trim_ascii());
+        Self {
+            id,
+            draws: &line[colon + 1..],
+        }
+    }
+
+    pub fn draws(&self) -> impl Iterator<Item = Draw> + 'a {
+        self.draws.split(|&byte| byte == b';').map(|draw| {
+            let mut cubes = Draw::default();
+            for (count, name) in draw_entries(draw) {
+                *cubes.get_mut(Colour::from_name(name)) += count;
+            }
+            cubes
+        })
+    }
+
+    /// Parses the draws with any colour names, adding new colours to the table as they are seen.
+    pub fn to_coloured_game(&self, colours: &mut ColourTable) -> ColouredGame {
+        ColouredGame {
+            id: self.id,
+            draws: self
+                .draws
+                .split(|&byte| byte == b';')
+                .map(|draw| {
+                    draw_entries(draw)
+                        .map(|(count, name)| (colours.intern(name), count))
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    pub fn is_possible_with(&self, bag: Draw) -> bool {
+        self.draws().all(|draw| draw.fits_in(bag))
+    }
+
+    pub fn minimum_bag(&self) -> Draw {
+        self.draws().fold(Draw::default(), Draw::max)
+    }
+
+    pub fn power(&self) -> u64 {
+        self.minimum_bag().power()
+    }
+
+    pub fn max(&self, colour: Colour) -> u32 {
+        self.draws().map(|draw| draw.get(colour)).max().unwrap_or(0)
+    }
+
+    pub fn to_game(&self) -> Game {
+        Game {
+            id: self.id,
+            draws: self.draws().collect(),
+        }
+    }
+}
+
+/// Iterates over the games in the input without copying or allocating.
+pub fn games(input: &[u8]) -> impl Iterator<Item = GameRef<'_>> {
+    input
+        .split(|&byte| byte == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(GameRef::parse)
+}
+
+pub fn parse_games(input: &[u8]) -> Vec<Game> {
+    games(input).map(|game| game.to_game()).collect()
+}
+
+/// Splits a draw like ` 3 blue, 4 red` into its counts and colour names.
+fn draw_entries(draw: &[u8]) -> impl Iterator<Item = (u32, &[u8])> {
+    draw.split(|&byte| byte == b',').map(|entry| {
+        let entry = entry.trim_ascii();
+        let space = memchr::memchr(b' ', entry).unwrap();
+        (parse_u32(&entry[..space]), entry[space + 1..].trim_ascii())
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ColourId(pub usize);
+
+/// Gives each distinct colour name a small index, in the order the names are first seen.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ColourTable {
+    names: Vec<Box<[u8]>>,
+}
+
+impl ColourTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A table with `red`, `green`, and `blue` already interned, in that order.
+    pub fn standard() -> Self {
+        let mut table = Self::new();
+        for name in [b"red" as &[u8], b"green", b"blue"] {
+            table.intern(name);
+        }
+        table
+    }
+
+    pub fn intern(&mut self, name: &[u8]) -> ColourId {
+        match self.get(name) {
+            Some(id) => id,
+            None => {
+                self.names.push(name.into());
+                ColourId(self.names.len() - 1)
+            }
+        }
+    }
+
+    pub fn get(&self, name: &[u8]) -> Option<ColourId> {
+        self.names
+            .iter()
+            .position(|known| **known == *name)
+            .map(ColourId)
+    }
+
+    pub fn name(&self, id: ColourId) -> &[u8] {
+        &self.names[id.0]
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+/// A draw with any number of colours. A colour may appear more than once, in which case the
+/// counts are added together.
+pub type ColouredDraw = Vec<(ColourId, u32)>;
+
+/// A game using colours from a [`ColourTable`]. Bags are described as a list of counts indexed by
+/// [`ColourId`], where colours past the end of the list have a count of zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColouredGame {
+    pub id: u32,
+    pub draws: Vec<ColouredDraw>,
+}
+
+impl ColouredGame {
+    fn totals(draw: &ColouredDraw, colours: usize) -> Vec<u32> {
+        let mut totals = vec![0; colours];
+        for &(colour, count) in draw {
+            if totals.len() <= colour.0 {
+                totals.resize(colour.0 + 1, 0);
+            }
+            totals[colour.0] += count;
+        }
+        totals
+    }
+
+    pub fn is_possible_with(&self, bag: &[u32]) -> bool {
+        self.draws.iter().all(|draw| {
+            Self::totals(draw, bag.len())
+                .iter()
+                .enumerate()
+                .all(|(colour, &count)| count <= bag.get(colour).copied().unwrap_or(0))
+        })
+    }
+
+    /// The minimum number of cubes of each colour, indexed by [`ColourId`], with an entry for
+    /// every colour in the table.
+    pub fn minimum_bag(&self, colours: &ColourTable) -> Vec<u32> {
+        let mut bag = vec![0; colours.len()];
+        for draw in &self.draws {
+            for (colour, count) in Self::totals(draw, colours.len()).into_iter().enumerate() {
+                bag[colour] = bag[colour].max(count);
+            }
+        }
+        bag
+    }
+
+    /// The product of the minimum bag over every colour in the table, so a game that never shows
+    /// one of the colours has a power of zero.
+    pub fn power(&self, colours: &ColourTable) -> u64 {
+        self.minimum_bag(colours)
+            .into_iter()
+            .map(u64::from)
+            .product()
+    }
+
+    pub fn max(&self, colour: ColourId) -> u32 {
+        self.draws
+            .iter()
+            .map(|draw| {
+                draw.iter()
+                    .filter(|(id, _)| *id == colour)
+                    .map(|(_, count)| count)
+                    .sum()
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+pub fn parse_coloured_games(input: &[u8], colours: &mut ColourTable) -> Vec<ColouredGame> {
+    games(input)
+        .map(|game| game.to_coloured_game(colours))
+        .collect()
+}
+
+/// Like `day2_part1_with_bag`, but for any colour names and counts. Colours that are not in the
+/// bag are treated as having no cubes.
+pub fn day2_part1_any_colours(input: &[u8], bag: &[(&str, u32)]) -> u32 {
+    let mut colours = ColourTable::new();
+    let mut counts = Vec::new();
+    for &(name, count) in bag {
+        let id = colours.intern(name.as_bytes());
+        counts.resize(counts.len().max(id.0 + 1), 0);
+        counts[id.0] = count;
+    }
+    parse_coloured_games(input, &mut colours)
+        .into_iter()
+        .filter(|game| game.is_possible_with(&counts))
+        .map(|game| game.id)
+        .sum()
+}
+
+/// Like `day2_part2`, but for any colour names and counts. The power is taken over every colour
+/// that appears anywhere in the input.
+pub fn day2_part2_any_colours(input: &[u8]) -> u64 {
+    let mut colours = ColourTable::new();
+    let games = parse_coloured_games(input, &mut colours);
+    games.iter().map(|game| game.power(&colours)).sum()
+}
+
+fn parse_u32(input: &[u8]) -> u32 {
+    input
+        .iter()
+        .fold(0, |acc, &digit| acc * 10 + u32::from(digit - b'0'))
+}
+
 fn power(input: &[u8]) -> (u32, usize) {
     // skup "Game" header text and id
     let mut index = 5;
@@ -34,14 +404,15 @@ fn power(input: &[u8]) -> (u32, usize) {
     (blue * green * red, index + bytes_read)
 }
 
-fn id_or_zero(input: &[u8]) -> (u32, usize) {
+fn possible_id(input: &[u8], bag: Draw) -> (Option<u32>, usize) {
     // skip "Game" header text
     let mut index = 5;
-    let (id, bytes_read) = parse_number(&input[index..], b':');
-    index += bytes_read + 1;
-    let (large, bytes_read) = is_large_game(&input[index..]);
+    let colon = index + find_byte(&input[index..], b':') - 1;
+    let id = parse_u32(&input[index..colon]);
+    index = colon + 2;
+    let (large, bytes_read) = is_large_game(&input[index..], bag);
     index += bytes_read;
-    (if large { 0 } else { id.into() }, index)
+    (if large { None } else { Some(id) }, index)
 }
 
 fn parse_number(input: &[u8], sep: u8) -> (u8, usize) {
@@ -90,28 +461,28 @@ fn minimal_cubes(input: &[u8]) -> ((u8, u8, u8), usize) {
     ((blues, greens, reds), index + 1)
 }
 
-fn is_large_game(input: &[u8]) -> (bool, usize) {
+fn is_large_game(input: &[u8], bag: Draw) -> (bool, usize) {
     let mut index = 0;
     loop {
         let (n, bytes) = parse_number(&input[index..], b' ');
         index += bytes;
         match input[index] {
             b'b' => {
-                if n > 14 {
+                if u32::from(n) > bag.blue {
                     return (true, find_byte(&input[index + 4..], b'\n') + index + 4);
                 } else {
                     index += 4
                 }
             }
             b'g' => {
-                if n > 13 {
+                if u32::from(n) > bag.green {
                     return (true, find_byte(&input[index + 5..], b'\n') + index + 5);
                 } else {
                     index += 5
                 }
             }
             b'r' => {
-                if n > 12 {
+                if u32::from(n) > bag.red {
                     return (true, find_byte(&input[index + 3..], b'\n') + index + 3);
                 } else {
                     index += 3
@@ -152,9 +523,68 @@ pub mod tests {
 
     #[test]
     fn is_large_game_detects_large_games() {
-        assert_eq!(is_large_game(b"123 red\n"), (true, 8));
-        assert_eq!(is_large_game(b"0 red, 123 green\n"), (true, 17));
-        assert_eq!(is_large_game(b"0 red; 123 blue\n"), (true, 16));
+        assert_eq!(is_large_game(b"123 red\n", STANDARD_BAG), (true, 8));
+        assert_eq!(
+            is_large_game(b"0 red, 123 green\n", STANDARD_BAG),
+            (true, 17)
+        );
+        assert_eq!(
+            is_large_game(b"0 red; 123 blue\n", STANDARD_BAG),
+            (true, 16)
+        );
+    }
+
+    #[test]
+    fn is_large_game_uses_given_bag() {
+        let bag = Draw::new(1, 2, 3);
+        assert_eq!(is_large_game(b"1 red, 2 green, 3 blue\n", bag), (false, 23));
+        assert_eq!(is_large_game(b"2 red\n", bag), (true, 6));
+        assert_eq!(is_large_game(b"1 red; 3 green\n", bag), (true, 15));
+        assert_eq!(is_large_game(b"4 blue\n", bag), (true, 7));
+        let bag = Draw::new(0, 300, 0);
+        assert_eq!(is_large_game(b"255 green\n", bag), (false, 10));
+        assert_eq!(is_large_game(b"1 blue\n", bag), (true, 7));
+    }
+
+    #[test]
+    fn possible_games_lists_feasible_ids() {
+        let input = utils::load_example(2);
+        assert_eq!(possible_games(&input, STANDARD_BAG), vec![1, 2, 5]);
+        assert_eq!(possible_games(&input, Draw::new(0, 0, 0)), vec![]);
+        assert_eq!(
+            possible_games(&input, Draw::new(20, 13, 14)),
+            vec![1, 2, 3, 5]
+        );
+        assert_eq!(day2_part1_with_bag(&input, Draw::new(20, 13, 14)), 11);
+    }
+
+    #[test]
+    fn possible_games_reads_ids_past_255() {
+        let input = b"Game 300: 1 red\nGame 70000: 20 blue\nGame 70001: 2 green, 3 blue\n";
+        assert_eq!(possible_games(input, STANDARD_BAG), vec![300, 70001]);
+        assert_eq!(day2_part1(input), 70301);
+    }
+
+    #[test]
+    fn possible_games_agrees_with_game_model() {
+        let input = utils::load_real(2);
+        for bag in [
+            STANDARD_BAG,
+            Draw::new(1, 1, 1),
+            Draw::new(20, 5, 10),
+            Draw::new(100, 100, 100),
+        ] {
+            let expected = parse_games(&input)
+                .into_iter()
+                .filter(|game| game.is_possible_with(bag))
+                .map(|game| game.id)
+                .collect::<Vec<_>>();
+            assert_eq!(possible_games(&input, bag), expected);
+            assert_eq!(
+                day2_part1_with_bag(&input, bag),
+                expected.iter().sum::<u32>()
+            );
+        }
     }
 
     #[test]
@@ -164,6 +594,149 @@ pub mod tests {
         assert_eq!(minimal_cubes(b"3 red; 4 red\n"), ((0, 0, 4), 13));
     }
 
+    #[test]
+    fn parses_games_into_draws() {
+        let games = parse_games(
+            b"Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green\nGame 12: 300 red\n",
+        );
+        assert_eq!(
+            games,
+            vec![
+                Game {
+                    id: 1,
+                    draws: vec![Draw::new(4, 0, 3), Draw::new(1, 2, 6), Draw::new(0, 2, 0)],
+                },
+                Game {
+                    id: 12,
+                    draws: vec![Draw::new(300, 0, 0)],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn game_queries_match_the_puzzle_description() {
+        let input = utils::load_example(2);
+        let games = parse_games(&input);
+        let bag = Draw::new(12, 13, 14);
+        assert_eq!(
+            games
+                .iter()
+                .map(|game| game.is_possible_with(bag))
+                .collect::<Vec<_>>(),
+            vec![true, true, false, false, true]
+        );
+        assert_eq!(games[0].minimum_bag(), Draw::new(4, 2, 6));
+        assert_eq!(games[0].power(), 48);
+        assert_eq!(games[2].max(Colour::Red), 20);
+        assert_eq!(games[2].max(Colour::Green), 13);
+        assert_eq!(games[2].max(Colour::Blue), 6);
+    }
+
+    #[test]
+    fn power_does_not_overflow_with_large_counts() {
+        let parsed = parse_games(b"Game 1: 3000 red, 3000 green, 3000 blue\n");
+        assert_eq!(parsed[0].power(), 27_000_000_000);
+        let game = games(b"Game 1: 3000 red, 3000 green; 3000 blue\n")
+            .next()
+            .unwrap();
+        assert_eq!(game.power(), 27_000_000_000);
+    }
+
+    #[test]
+    fn game_refs_agree_with_parsed_games() {
+        let input = utils::load_real(2);
+        let bag = Draw::new(12, 13, 14);
+        for (game_ref, game) in games(&input).zip(parse_games(&input)) {
+            assert_eq!(game_ref.to_game(), game);
+            assert_eq!(game_ref.is_possible_with(bag), game.is_possible_with(bag));
+            assert_eq!(game_ref.minimum_bag(), game.minimum_bag());
+            assert_eq!(game_ref.max(Colour::Blue), game.max(Colour::Blue));
+        }
+        assert_eq!(
+            games(&input)
+                .filter(|game| game.is_possible_with(bag))
+                .map(|game| game.id)
+                .sum::<u32>(),
+            2283
+        );
+        assert_eq!(games(&input).map(|game| game.power()).sum::<u64>(), 78669);
+    }
+
+    #[test]
+    fn colour_table_interns_names() {
+        let mut colours = ColourTable::standard();
+        assert_eq!(colours.get(b"green"), Some(ColourId(1)));
+        assert_eq!(colours.intern(b"purple"), ColourId(3));
+        assert_eq!(colours.intern(b"blue"), ColourId(2));
+        assert_eq!(colours.intern(b"purple"), ColourId(3));
+        assert_eq!(colours.name(ColourId(3)), b"purple");
+        assert_eq!(colours.len(), 4);
+        assert_eq!(colours.get(b"bluish"), None);
+    }
+
+    #[test]
+    fn parses_games_with_any_colours_and_large_counts() {
+        let input = b"Game 1: 300 purple, 2 blue; 1000 brown\nGame 2: 7 blue, 1 bright red\n";
+        let mut colours = ColourTable::new();
+        let games = parse_coloured_games(input, &mut colours);
+        let (purple, blue, brown, bright_red) =
+            (ColourId(0), ColourId(1), ColourId(2), ColourId(3));
+        assert_eq!(colours.name(bright_red), b"bright red");
+        assert_eq!(
+            games,
+            vec![
+                ColouredGame {
+                    id: 1,
+                    draws: vec![vec![(purple, 300), (blue, 2)], vec![(brown, 1000)]],
+                },
+                ColouredGame {
+                    id: 2,
+                    draws: vec![vec![(blue, 7), (bright_red, 1)]],
+                },
+            ]
+        );
+        assert_eq!(games[0].minimum_bag(&colours), vec![300, 2, 1000, 0]);
+        assert_eq!(games[0].max(brown), 1000);
+        assert_eq!(games[1].max(purple), 0);
+        assert_eq!(games[0].power(&colours), 0);
+        assert!(games[0].is_possible_with(&[300, 2, 1000]));
+        assert!(!games[0].is_possible_with(&[299, 2, 1000]));
+        assert!(!games[1].is_possible_with(&[300, 7, 1000]));
+    }
+
+    #[test]
+    fn any_colour_functions_handle_new_colours() {
+        let input =
+            b"Game 1: 300 purple, 2 blue; 1000 brown\nGame 2: 7 blue, 2 blue; 1 purple, 3 brown\n";
+        assert_eq!(
+            day2_part1_any_colours(input, &[("purple", 300), ("blue", 9), ("brown", 1000)]),
+            3
+        );
+        assert_eq!(
+            day2_part1_any_colours(input, &[("purple", 300), ("blue", 8)]),
+            0
+        );
+        assert_eq!(
+            day2_part1_any_colours(input, &[("blue", 9), ("purple", 1), ("brown", 3)]),
+            2
+        );
+        assert_eq!(day2_part2_any_colours(input), 300 * 2 * 1000 + 9 * 3);
+    }
+
+    #[test]
+    fn any_colour_functions_match_fast_path() {
+        let input = utils::load_real(2);
+        assert_eq!(
+            day2_part1_any_colours(&input, &[("red", 12), ("green", 13), ("blue", 14)]),
+            day2_part1(&input)
+        );
+        assert_eq!(
+            day2_part2_any_colours(&input),
+            u64::from(day2_part2(&input))
+        );
+    }
+
     #[test]
     fn test_day2_part1_example() {
         let input = utils::load_example(2);
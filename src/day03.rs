@@ -1,34 +1,187 @@
+use std::{
+    ops::Range,
+    simd::{prelude::*, u8x64},
+};
+
 use arrayvec::ArrayVec;
-use memchr::{memchr3_iter, memchr_iter};
+use memchr::memchr_iter;
+
+/// The bytes that should be treated as symbols when looking for part numbers.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum SymbolSet {
+    /// Any byte that is not a digit, a `.`, or a newline.
+    #[default]
+    Any,
+    /// Only the given bytes.
+    Only(Vec<u8>),
+}
+
+impl SymbolSet {
+    pub fn contains(&self, byte: u8) -> bool {
+        match self {
+            SymbolSet::Any => !matches!(byte, b'0'..=b'9' | b'.' | b'\n'),
+            SymbolSet::Only(symbols) => symbols.contains(&byte),
+        }
+    }
+
+    fn classify(&self, chunk: u8x64) -> u64 {
+        match self {
+            SymbolSet::Any => {
+                let digits = chunk.simd_ge(u8x64::splat(b'0')) & chunk.simd_le(u8x64::splat(b'9'));
+                let dots = chunk.simd_eq(u8x64::splat(b'.'));
+                let newlines = chunk.simd_eq(u8x64::splat(b'\n'));
+                (!(digits | dots | newlines)).to_bitmask()
+            }
+            SymbolSet::Only(symbols) => symbols
+                .iter()
+                .fold(mask8x64::splat(false), |mask, &symbol| {
+                    mask | chunk.simd_eq(u8x64::splat(symbol))
+                })
+                .to_bitmask(),
+        }
+    }
+}
+
+struct SymbolIndices<'a> {
+    grid: &'a [u8],
+    symbols: &'a SymbolSet,
+    offset: usize,
+    mask: u64,
+}
+
+impl Iterator for SymbolIndices<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.mask == 0 {
+            if self.offset >= self.grid.len() {
+                return None;
+            }
+            let rest = &self.grid[self.offset..];
+            let chunk = match rest.get(..u8x64::LEN) {
+                Some(full) => u8x64::from_slice(full),
+                None => u8x64::load_or(rest, u8x64::splat(b'.')),
+            };
+            self.mask = self.symbols.classify(chunk);
+            self.offset += u8x64::LEN;
+        }
+
+        let bit = self.mask.trailing_zeros() as usize;
+        self.mask &= self.mask - 1;
+        Some(self.offset - u8x64::LEN + bit)
+    }
+}
+
+/// A number found in the schematic, with its row and the columns it covers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartNumber {
+    pub value: u32,
+    pub row: usize,
+    pub columns: Range<usize>,
+}
+
+/// A symbol found in the schematic, with its row and column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Symbol {
+    pub symbol: u8,
+    pub row: usize,
+    pub column: usize,
+}
+
+/// A part number along with every symbol it touches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdjacentPart {
+    pub number: PartNumber,
+    pub symbols: Vec<Symbol>,
+}
+
+/// Every number in the schematic, split into the ones that touch at least one symbol and the
+/// ones that touch none.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct PartReport {
+    pub parts: Vec<AdjacentPart>,
+    pub unattached: Vec<PartNumber>,
+}
+
+trait Coordinate: Copy + std::fmt::Debug + PartialEq {
+    /// The largest grid dimension that can be indexed, leaving room to step one cell outside the
+    /// grid on either side.
+    const MAX_DIMENSION: usize;
+
+    fn from_index(index: usize) -> Self;
+    /// Returns `None` for positions left of or above the grid.
+    fn to_index(self) -> Option<usize>;
+    fn increment(self) -> Self;
+    fn decrement(self) -> Self;
+}
+
+macro_rules! impl_coordinate {
+    ($($ty:ty),*) => {
+        $(
+            impl Coordinate for $ty {
+                const MAX_DIMENSION: usize = <$ty>::MAX as usize - 1;
+
+                #[inline(always)]
+                fn from_index(index: usize) -> Self {
+                    index as $ty
+                }
+
+                #[inline(always)]
+                fn to_index(self) -> Option<usize> {
+                    usize::try_from(self).ok()
+                }
+
+                #[inline(always)]
+                fn increment(self) -> Self {
+                    self + 1
+                }
+
+                #[inline(always)]
+                fn decrement(self) -> Self {
+                    self - 1
+                }
+            }
+        )*
+    };
+}
+
+impl_coordinate!(i16, i32, isize);
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-struct Position {
-    x: i16,
-    y: i16,
+struct Position<C> {
+    x: C,
+    y: C,
 }
 
-impl Position {
-    fn new(x: i16, y: i16) -> Self {
+impl<C: Coordinate> Position<C> {
+    fn new(x: C, y: C) -> Self {
         Self { x, y }
     }
 
+    fn from_index(index: usize, width: usize) -> Self {
+        Self::new(
+            C::from_index(index % (width + 1)),
+            C::from_index(index / (width + 1)),
+        )
+    }
+
     fn up(mut self) -> Self {
-        self.y -= 1;
+        self.y = self.y.decrement();
         self
     }
 
     fn down(mut self) -> Self {
-        self.y += 1;
+        self.y = self.y.increment();
         self
     }
 
     fn left(mut self) -> Self {
-        self.x -= 1;
+        self.x = self.x.decrement();
         self
     }
 
     fn right(mut self) -> Self {
-        self.x += 1;
+        self.x = self.x.increment();
         self
     }
 }
@@ -51,60 +204,257 @@ impl<'a> Grid<'a> {
         }
     }
 
-    fn get(&self, index: Position) -> &u8 {
-        if index.x < 0 || index.y < 0 {
-            &b'.'
-        } else {
-            let x = usize::try_from(index.x).unwrap();
-            let y = usize::try_from(index.y).unwrap();
-            if x >= self.width || y >= self.height {
-                &b'.'
-            } else {
+    /// Whether every position in (and directly around) the grid can be represented using the
+    /// coordinate type `C`.
+    fn fits<C: Coordinate>(&self) -> bool {
+        self.width <= C::MAX_DIMENSION && self.height <= C::MAX_DIMENSION
+    }
+
+    fn get<C: Coordinate>(&self, index: Position<C>) -> &u8 {
+        match (index.x.to_index(), index.y.to_index()) {
+            (Some(x), Some(y)) if x < self.width && y < self.height => {
                 &self.grid[y * (self.width + 1) + x]
             }
+            _ => &b'.',
         }
     }
 
-    fn slice_after(&self, index: Position) -> &[u8] {
-        let x = usize::try_from(index.x).unwrap();
-        let y = usize::try_from(index.y).unwrap();
-        &self.grid[(y * (self.width + 1) + x)..]
+    fn get_at(&self, column: usize, row: usize) -> u8 {
+        if column >= self.width || row >= self.height {
+            b'.'
+        } else {
+            self.grid[row * (self.width + 1) + column]
+        }
     }
 
-    fn symbols(&self) -> impl Iterator<Item = Position> + '_ {
-        // Note: this list of symbols was obtained manually by searching through the input text.
-        memchr3_iter(b'*', b'@', b'#', self.grid)
-            .chain(memchr3_iter(b'+', b'=', b'$', self.grid))
-            .chain(memchr3_iter(b'%', b'/', b'&', self.grid))
-            .chain(memchr_iter(b'-', self.grid))
-            .map(|index| {
-                Position::new(
-                    (index % (self.width + 1)).try_into().unwrap(),
-                    (index / (self.width + 1)).try_into().unwrap(),
-                )
+    fn numbers(&self) -> impl Iterator<Item = PartNumber> + '_ {
+        (0..self.height).flat_map(move |row| {
+            let line = &self.grid[row * (self.width + 1)..][..self.width];
+            let mut column = 0;
+            std::iter::from_fn(move || {
+                while column < line.len() && !line[column].is_ascii_digit() {
+                    column += 1;
+                }
+                if column >= line.len() {
+                    return None;
+                }
+                let start = column;
+                let mut value = 0;
+                while let Some(c @ b'0'..=b'9') = line.get(column) {
+                    value = value * 10 + u32::from(c - b'0');
+                    column += 1;
+                }
+                Some(PartNumber {
+                    value,
+                    row,
+                    columns: start..column,
+                })
             })
+        })
     }
 
-    fn stars(&self) -> impl Iterator<Item = Position> + '_ {
-        memchr_iter(b'*', self.grid).map(|index| {
-            Position::new(
-                (index % (self.width + 1)).try_into().unwrap(),
-                (index / (self.width + 1)).try_into().unwrap(),
-            )
-        })
+    fn adjacent_symbols<'s>(
+        &'s self,
+        number: &PartNumber,
+        symbols: &'s SymbolSet,
+    ) -> impl Iterator<Item = Symbol> + 's {
+        let rows = number.row.saturating_sub(1)..number.row + 2;
+        let columns = number.columns.start.saturating_sub(1)..number.columns.end + 1;
+        rows.flat_map(move |row| columns.clone().map(move |column| (row, column)))
+            .filter_map(move |(row, column)| {
+                let symbol = self.get_at(column, row);
+                symbols.contains(symbol).then_some(Symbol {
+                    symbol,
+                    row,
+                    column,
+                })
+            })
+    }
+
+    fn slice_after<C: Coordinate>(&self, index: Position<C>) -> &[u8] {
+        let x = index.x.to_index().unwrap();
+        let y = index.y.to_index().unwrap();
+        &self.grid[(y * (self.width + 1) + x)..]
+    }
+
+    fn symbols<C: Coordinate>(&self) -> impl Iterator<Item = Position<C>> + '_ {
+        self.symbols_in(&SymbolSet::Any)
+    }
+
+    fn symbols_in<'s, C: Coordinate>(
+        &'s self,
+        symbols: &'s SymbolSet,
+    ) -> impl Iterator<Item = Position<C>> + 's {
+        SymbolIndices {
+            grid: self.grid,
+            symbols,
+            offset: 0,
+            mask: 0,
+        }
+        .map(|index| Position::from_index(index, self.width))
+    }
+
+    fn stars<C: Coordinate>(&self) -> impl Iterator<Item = Position<C>> + '_ {
+        memchr_iter(b'*', self.grid).map(|index| Position::from_index(index, self.width))
     }
 }
 
+/// Calls the given expression with a grid coordinate type `$c` that is large enough for `$grid`.
+/// Official inputs fit into `i16`, which keeps the hot loops small, while larger grids fall back
+/// to `i32` and then `isize`.
+macro_rules! with_coordinate {
+    ($grid:expr, |$c:ident| $body:expr) => {
+        if $grid.fits::<i16>() {
+            type $c = i16;
+            $body
+        } else if $grid.fits::<i32>() {
+            type $c = i32;
+            $body
+        } else {
+            type $c = isize;
+            $body
+        }
+    };
+}
+
 pub fn day3_part1(input: &[u8]) -> u32 {
     let grid = Grid::new(input);
-    grid.symbols()
-        .map(|p| numbers_for_symbol(&grid, p).iter().sum::<u32>())
+    with_coordinate!(grid, |C| sum_numbers_for_symbols(
+        &grid,
+        grid.symbols::<C>()
+    ))
+}
+
+pub fn day3_part1_with_symbols(input: &[u8], symbols: &SymbolSet) -> u32 {
+    let grid = Grid::new(input);
+    with_coordinate!(grid, |C| sum_numbers_for_symbols(
+        &grid,
+        grid.symbols_in::<C>(symbols)
+    ))
+}
+
+/// Sums each number touching at least one symbol exactly once, rather than once per symbol it
+/// touches.
+pub fn day3_part1_distinct(input: &[u8], symbols: &SymbolSet) -> u32 {
+    let grid = Grid::new(input);
+    grid.numbers()
+        .filter(|number| grid.adjacent_symbols(number, symbols).next().is_some())
+        .map(|number| number.value)
+        .sum()
+}
+
+/// Scans the grid row by row, keeping digit and symbol masks for the previous, current, and next
+/// rows. Each number is parsed exactly once, so like [`day3_part1_distinct`], numbers touching
+/// multiple symbols are only counted once.
+pub fn day3_part1_single_pass(input: &[u8]) -> u32 {
+    let grid = Grid::new(input);
+    let words = grid.width.div_ceil(64);
+    let row = |index: usize| &grid.grid[index * (grid.width + 1)..][..grid.width];
+
+    let mut digits = vec![0; words];
+    let mut next_digits = vec![0; words];
+    let mut previous_symbols = vec![0; words];
+    let mut symbols = vec![0; words];
+    let mut next_symbols = vec![0; words];
+    let mut adjacent = vec![0; words];
+
+    if grid.height > 0 {
+        row_masks(row(0), &mut next_digits, &mut next_symbols);
+    }
+
+    let mut sum = 0;
+    for index in 0..grid.height {
+        std::mem::swap(&mut digits, &mut next_digits);
+        std::mem::swap(&mut previous_symbols, &mut symbols);
+        std::mem::swap(&mut symbols, &mut next_symbols);
+        if index + 1 < grid.height {
+            row_masks(row(index + 1), &mut next_digits, &mut next_symbols);
+        } else {
+            next_symbols.fill(0);
+        }
+
+        // Spread the symbols from all three rows one column to the left and right, so a number is
+        // a part number exactly when one of its own columns is set.
+        let mut carry = 0;
+        for word in 0..words {
+            let vertical = previous_symbols[word] | symbols[word] | next_symbols[word];
+            let following = match next_symbols.get(word + 1) {
+                Some(next) => previous_symbols[word + 1] | symbols[word + 1] | next,
+                None => 0,
+            };
+            adjacent[word] =
+                vertical | (vertical << 1) | carry | (vertical >> 1) | (following << 63);
+            carry = vertical >> 63;
+        }
+
+        // Unlike `row`, this includes the trailing newline, which terminates `parse_number`.
+        let line = &grid.grid[index * (grid.width + 1)..];
+        let mut column = 0;
+        while let Some(start) = next_bit(&digits, column, false) {
+            let end = next_bit(&digits, start, true).unwrap_or(grid.width);
+            if any_bit(&adjacent, start..end) {
+                sum += parse_number(&line[start..]).0;
+            }
+            column = end;
+        }
+    }
+
+    sum
+}
+
+fn row_masks(row: &[u8], digits: &mut [u64], symbols: &mut [u64]) {
+    for (word, chunk) in row.chunks(u8x64::LEN).enumerate() {
+        let chunk = match chunk.try_into() {
+            Ok(full) => u8x64::from_array(full),
+            Err(_) => u8x64::load_or(chunk, u8x64::splat(b'.')),
+        };
+        digits[word] =
+            (chunk.simd_ge(u8x64::splat(b'0')) & chunk.simd_le(u8x64::splat(b'9'))).to_bitmask();
+        symbols[word] = SymbolSet::Any.classify(chunk);
+    }
+}
+
+/// Finds the first column from `from` onwards whose bit is set (or unset, if `inverted`).
+fn next_bit(mask: &[u64], from: usize, inverted: bool) -> Option<usize> {
+    let mut word = from / 64;
+    let mut bits = mask.get(word)? ^ if inverted { u64::MAX } else { 0 };
+    bits &= u64::MAX << (from % 64);
+    loop {
+        if bits != 0 {
+            return Some(word * 64 + bits.trailing_zeros() as usize);
+        }
+        word += 1;
+        bits = mask.get(word)? ^ if inverted { u64::MAX } else { 0 };
+    }
+}
+
+fn any_bit(mask: &[u64], columns: Range<usize>) -> bool {
+    let (first, last) = (columns.start / 64, (columns.end - 1) / 64);
+    (first..=last).any(|word| {
+        let mut bits = mask[word];
+        if word == first {
+            bits &= u64::MAX << (columns.start % 64);
+        }
+        if word == last {
+            bits &= u64::MAX >> (63 - (columns.end - 1) % 64);
+        }
+        bits != 0
+    })
+}
+
+fn sum_numbers_for_symbols<C: Coordinate>(
+    grid: &Grid<'_>,
+    symbols: impl Iterator<Item = Position<C>>,
+) -> u32 {
+    symbols
+        .map(|p| numbers_for_symbol(grid, p).iter().sum::<u32>())
         .sum()
 }
 
 pub fn day3_part2(input: &[u8]) -> u32 {
     let grid = Grid::new(input);
-    grid.stars()
+    with_coordinate!(grid, |C| grid
+        .stars::<C>()
         .filter_map(|position| {
             let numbers = numbers_for_symbol(&grid, position);
             if numbers.len() == 2 {
@@ -113,7 +463,153 @@ pub fn day3_part2(input: &[u8]) -> u32 {
                 None
             }
         })
-        .sum()
+        .sum())
+}
+
+/// How many numbers must surround a symbol for it to count as a gear.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AdjacentCount {
+    Exactly(usize),
+    AtLeast(usize),
+}
+
+impl AdjacentCount {
+    fn matches(self, count: usize) -> bool {
+        match self {
+            AdjacentCount::Exactly(n) => count == n,
+            AdjacentCount::AtLeast(n) => count >= n,
+        }
+    }
+}
+
+/// How the numbers around a gear are combined into its ratio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reducer {
+    Product,
+    Sum,
+    Max,
+}
+
+impl Reducer {
+    /// The ratio of the numbers around a gear, or `None` if it overflows a `u64`. A symbol with no
+    /// numbers around it has a ratio of 0, whichever the reducer.
+    fn reduce(self, numbers: &[u32]) -> Option<u64> {
+        if numbers.is_empty() {
+            return Some(0);
+        }
+        let mut numbers = numbers.iter().map(|&n| u64::from(n));
+        match self {
+            Reducer::Product => numbers.try_fold(1, u64::checked_mul),
+            Reducer::Sum => numbers.try_fold(0, u64::checked_add),
+            Reducer::Max => numbers.max(),
+        }
+    }
+}
+
+/// Decides which symbols are gears and how their ratios are calculated. The default rule is the
+/// one from the puzzle: a `*` with exactly two adjacent numbers, multiplied together.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GearRule {
+    pub symbols: SymbolSet,
+    pub count: AdjacentCount,
+    pub reducer: Reducer,
+}
+
+impl Default for GearRule {
+    fn default() -> Self {
+        Self {
+            symbols: SymbolSet::Only(vec![b'*']),
+            count: AdjacentCount::Exactly(2),
+            reducer: Reducer::Product,
+        }
+    }
+}
+
+/// A symbol that satisfied a [`GearRule`], with the numbers around it and its reduced ratio.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gear {
+    pub symbol: Symbol,
+    pub numbers: Vec<u32>,
+    /// `None` if the ratio overflows a `u64`.
+    pub ratio: Option<u64>,
+}
+
+/// Sums the ratios of every gear, or returns `None` if a ratio or the sum overflows a `u64`. See
+/// [`gears`] to find which gear overflowed.
+pub fn day3_part2_with_rule(input: &[u8], rule: &GearRule) -> Option<u64> {
+    let grid = Grid::new(input);
+    with_coordinate!(grid, |C| sum_gear_ratios(
+        &grid,
+        grid.symbols_in::<C>(&rule.symbols),
+        rule
+    ))
+}
+
+fn sum_gear_ratios<C: Coordinate>(
+    grid: &Grid<'_>,
+    symbols: impl Iterator<Item = Position<C>>,
+    rule: &GearRule,
+) -> Option<u64> {
+    let mut numbers = Vec::new();
+    symbols
+        .filter_map(|position| {
+            numbers.clear();
+            for_each_number_around(grid, position, |value| numbers.push(value));
+            rule.count
+                .matches(numbers.len())
+                .then(|| rule.reducer.reduce(&numbers))
+        })
+        .try_fold(0, |total: u64, ratio| total.checked_add(ratio?))
+}
+
+/// Lists every symbol that satisfies the gear rule, along with its numbers and ratio.
+pub fn gears(input: &[u8], rule: &GearRule) -> Vec<Gear> {
+    let grid = Grid::new(input);
+    with_coordinate!(grid, |C| collect_gears(
+        &grid,
+        grid.symbols_in::<C>(&rule.symbols),
+        rule
+    ))
+}
+
+fn collect_gears<C: Coordinate>(
+    grid: &Grid<'_>,
+    symbols: impl Iterator<Item = Position<C>>,
+    rule: &GearRule,
+) -> Vec<Gear> {
+    symbols
+        .filter_map(|position| {
+            let mut numbers = Vec::new();
+            for_each_number_around(grid, position, |value| numbers.push(value));
+            rule.count.matches(numbers.len()).then(|| Gear {
+                symbol: Symbol {
+                    symbol: *grid.get(position),
+                    row: position.y.to_index().unwrap(),
+                    column: position.x.to_index().unwrap(),
+                },
+                ratio: rule.reducer.reduce(&numbers),
+                numbers,
+            })
+        })
+        .collect()
+}
+
+/// Lists every number in the schematic along with the symbols it touches.
+pub fn part_report(input: &[u8], symbols: &SymbolSet) -> PartReport {
+    let grid = Grid::new(input);
+    let mut report = PartReport::default();
+    for number in grid.numbers() {
+        let adjacent = grid.adjacent_symbols(&number, symbols).collect::<Vec<_>>();
+        if adjacent.is_empty() {
+            report.unattached.push(number);
+        } else {
+            report.parts.push(AdjacentPart {
+                number,
+                symbols: adjacent,
+            });
+        }
+    }
+    report
 }
 
 fn parse_number(input: &[u8]) -> (u32, usize) {
@@ -128,37 +624,48 @@ fn parse_number(input: &[u8]) -> (u32, usize) {
     (sum, pos)
 }
 
-fn numbers_for_symbol(input: &Grid<'_>, position: Position) -> ArrayVec<u32, 6> {
+fn numbers_for_symbol<C: Coordinate>(input: &Grid<'_>, position: Position<C>) -> ArrayVec<u32, 6> {
     let mut array = ArrayVec::new();
+    for_each_number_around(input, position, |value| array.push(value));
+    array
+}
 
+// There can be at most six numbers around a single symbol: one on either side, and up to two
+// above and below.
+#[inline(always)]
+fn for_each_number_around<C: Coordinate>(
+    input: &Grid<'_>,
+    position: Position<C>,
+    mut push: impl FnMut(u32),
+) {
     if let b'0'..=b'9' = *input.get(position.left()) {
         let start = find_number_start(input, position.left());
         let (value, _) = parse_number(input.slice_after(start));
-        array.push(value);
+        push(value);
     }
 
     if let b'0'..=b'9' = *input.get(position.right()) {
         let (value, _) = parse_number(input.slice_after(position.right()));
-        array.push(value);
+        push(value);
     }
 
     match *input.get(position.up()) {
         b'0'..=b'9' => {
             let start = find_number_start(input, position.up());
             let (value, _) = parse_number(input.slice_after(start));
-            array.push(value);
+            push(value);
         }
         _ => {
             let topleft = position.up().left();
             if let b'0'..=b'9' = *input.get(topleft) {
                 let start = find_number_start(input, topleft);
                 let (value, _) = parse_number(input.slice_after(start));
-                array.push(value);
+                push(value);
             }
             let topright = position.up().right();
             if let b'0'..=b'9' = *input.get(topright) {
                 let (value, _) = parse_number(input.slice_after(topright));
-                array.push(value);
+                push(value);
             }
         }
     }
@@ -167,27 +674,25 @@ fn numbers_for_symbol(input: &Grid<'_>, position: Position) -> ArrayVec<u32, 6>
         b'0'..=b'9' => {
             let start = find_number_start(input, position.down());
             let (value, _) = parse_number(input.slice_after(start));
-            array.push(value);
+            push(value);
         }
         _ => {
             let bottomleft = position.down().left();
             if let b'0'..=b'9' = *input.get(bottomleft) {
                 let start = find_number_start(input, bottomleft);
                 let (value, _) = parse_number(input.slice_after(start));
-                array.push(value);
+                push(value);
             }
             let bottomright = position.down().right();
             if let b'0'..=b'9' = *input.get(bottomright) {
                 let (value, _) = parse_number(input.slice_after(bottomright));
-                array.push(value);
+                push(value);
             }
         }
     }
-
-    array
 }
 
-fn find_number_start(grid: &Grid<'_>, mut position: Position) -> Position {
+fn find_number_start<C: Coordinate>(grid: &Grid<'_>, mut position: Position<C>) -> Position<C> {
     loop {
         let new = position.left();
         if let b'0'..=b'9' = *grid.get(new) {
@@ -339,6 +844,413 @@ pub mod tests {
         });
     }
 
+    #[test]
+    fn treats_every_punctuation_character_except_dot_as_a_symbol() {
+        for symbol in (b'!'..=b'~').filter(|c| c.is_ascii_punctuation() && *c != b'.') {
+            let grid = [b'1', b'2', b'3', symbol, b'\n'];
+            assert_eq!(day3_part1(&grid), 123, "symbol {:?}", symbol as char);
+            let grid = [b'.', b'.', symbol, b'\n', b'1', b'2', b'.', b'\n'];
+            assert_eq!(day3_part1(&grid), 12, "symbol {:?}", symbol as char);
+        }
+    }
+
+    #[test]
+    fn does_not_treat_dots_digits_or_newlines_as_symbols() {
+        assert_eq!(day3_part1(b"123.\n....\n"), 0);
+        assert_eq!(Grid::new(b"12.\n.4.\n").symbols::<i16>().next(), None);
+    }
+
+    #[test]
+    fn finds_symbols_beyond_the_first_simd_chunk() {
+        let mut input = vec![b'.'; 100];
+        input.extend_from_slice(b"7?\n");
+        let grid = Grid::new(&input);
+        assert_eq!(
+            grid.symbols().collect::<Vec<_>>(),
+            vec![Position::new(101, 0)]
+        );
+        assert_eq!(day3_part1(&input), 7);
+    }
+
+    #[test]
+    fn custom_symbol_set_only_matches_given_symbols() {
+        let input = b"1#2.\n....\n3*..\n";
+        let symbols = SymbolSet::Only(vec![b'#']);
+        assert_eq!(day3_part1_with_symbols(input, &symbols), 3);
+        let symbols = SymbolSet::Only(vec![b'#', b'*']);
+        assert_eq!(day3_part1_with_symbols(input, &symbols), 6);
+        let symbols = SymbolSet::Only(vec![b'a']);
+        assert_eq!(day3_part1_with_symbols(b"1a\n", &symbols), 1);
+    }
+
+    #[test]
+    fn part_report_lists_numbers_with_their_positions_and_symbols() {
+        let report = part_report(b"467...\n...*..\n.35..1\n", &SymbolSet::Any);
+        assert_eq!(
+            report,
+            PartReport {
+                parts: vec![
+                    AdjacentPart {
+                        number: PartNumber {
+                            value: 467,
+                            row: 0,
+                            columns: 0..3,
+                        },
+                        symbols: vec![Symbol {
+                            symbol: b'*',
+                            row: 1,
+                            column: 3,
+                        }],
+                    },
+                    AdjacentPart {
+                        number: PartNumber {
+                            value: 35,
+                            row: 2,
+                            columns: 1..3,
+                        },
+                        symbols: vec![Symbol {
+                            symbol: b'*',
+                            row: 1,
+                            column: 3,
+                        }],
+                    },
+                ],
+                unattached: vec![PartNumber {
+                    value: 1,
+                    row: 2,
+                    columns: 5..6,
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn part_report_lists_every_symbol_touching_a_number() {
+        let report = part_report(b"#..\n12$\n", &SymbolSet::Any);
+        assert_eq!(report.parts.len(), 1);
+        assert_eq!(
+            report.parts[0].symbols,
+            vec![
+                Symbol {
+                    symbol: b'#',
+                    row: 0,
+                    column: 0,
+                },
+                Symbol {
+                    symbol: b'$',
+                    row: 1,
+                    column: 2,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn part_report_matches_the_example() {
+        let input = utils::load_example(3);
+        let report = part_report(&input, &SymbolSet::Any);
+        assert_eq!(
+            report
+                .unattached
+                .iter()
+                .map(|number| number.value)
+                .collect::<Vec<_>>(),
+            vec![114, 58]
+        );
+        assert_eq!(
+            report
+                .parts
+                .iter()
+                .map(|part| part.number.value)
+                .sum::<u32>(),
+            4361
+        );
+    }
+
+    #[test]
+    fn distinct_counting_counts_numbers_shared_between_symbols_once() {
+        let input = b"*12#\n";
+        assert_eq!(day3_part1(input), 24);
+        assert_eq!(day3_part1_distinct(input, &SymbolSet::Any), 12);
+
+        let input = b".*.\n.5.\n.#.\n";
+        assert_eq!(day3_part1(input), 10);
+        assert_eq!(day3_part1_distinct(input, &SymbolSet::Any), 5);
+
+        let input = b"#..\n.7.\n..#\n.7.\n";
+        assert_eq!(day3_part1(input), 21);
+        assert_eq!(day3_part1_distinct(input, &SymbolSet::Any), 14);
+    }
+
+    #[test]
+    fn distinct_counting_keeps_equal_numbers_at_different_positions() {
+        let input = b"3*3\n";
+        assert_eq!(day3_part1_distinct(input, &SymbolSet::Any), 6);
+
+        let input = b"3.3\n.*.\n3.3\n";
+        assert_eq!(day3_part1(input), 12);
+        assert_eq!(day3_part1_distinct(input, &SymbolSet::Any), 12);
+    }
+
+    #[test]
+    fn test_day3_part1_distinct_example() {
+        let input = utils::load_example(3);
+        assert_eq!(day3_part1_distinct(&input, &SymbolSet::Any), 4361);
+    }
+
+    #[test]
+    fn test_day3_part1_distinct_real() {
+        let input = utils::load_real(3);
+        assert_eq!(day3_part1_distinct(&input, &SymbolSet::Any), 539637);
+    }
+
+    #[test]
+    fn default_gear_rule_matches_part2() {
+        let input = utils::load_example(3);
+        assert_eq!(
+            day3_part2_with_rule(&input, &GearRule::default()),
+            Some(467835)
+        );
+        let input = utils::load_real(3);
+        assert_eq!(
+            day3_part2_with_rule(&input, &GearRule::default()),
+            Some(82818007)
+        );
+    }
+
+    #[test]
+    fn gear_rule_supports_other_symbols_counts_and_reducers() {
+        let input = b"2.3\n.#.\n4.5\n1*1\n";
+        let rule = GearRule {
+            symbols: SymbolSet::Only(vec![b'#']),
+            count: AdjacentCount::Exactly(4),
+            reducer: Reducer::Product,
+        };
+        assert_eq!(day3_part2_with_rule(input, &rule), Some(120));
+
+        let rule = GearRule {
+            symbols: SymbolSet::Any,
+            count: AdjacentCount::AtLeast(2),
+            reducer: Reducer::Sum,
+        };
+        assert_eq!(day3_part2_with_rule(input, &rule), Some(14 + 11));
+
+        let rule = GearRule {
+            symbols: SymbolSet::Any,
+            count: AdjacentCount::AtLeast(1),
+            reducer: Reducer::Max,
+        };
+        assert_eq!(day3_part2_with_rule(input, &rule), Some(5 + 5));
+
+        let rule = GearRule {
+            symbols: SymbolSet::Any,
+            count: AdjacentCount::Exactly(3),
+            reducer: Reducer::Sum,
+        };
+        assert_eq!(day3_part2_with_rule(input, &rule), Some(0));
+    }
+
+    #[test]
+    fn gear_rule_handles_symbols_surrounded_by_six_numbers() {
+        let input = b"1.2\n3*4\n5.6\n";
+        let rule = GearRule {
+            symbols: SymbolSet::Any,
+            count: AdjacentCount::AtLeast(0),
+            reducer: Reducer::Product,
+        };
+        assert_eq!(
+            gears(input, &rule),
+            vec![Gear {
+                symbol: Symbol {
+                    symbol: b'*',
+                    row: 1,
+                    column: 1,
+                },
+                numbers: vec![3, 4, 1, 2, 5, 6],
+                ratio: Some(720),
+            }]
+        );
+    }
+
+    #[test]
+    fn gear_rule_gives_symbols_without_numbers_a_ratio_of_zero() {
+        let input = b"*..\n...\n..#\n";
+        for count in [AdjacentCount::AtLeast(0), AdjacentCount::Exactly(0)] {
+            for reducer in [Reducer::Product, Reducer::Sum, Reducer::Max] {
+                let rule = GearRule {
+                    symbols: SymbolSet::Any,
+                    count,
+                    reducer,
+                };
+                assert_eq!(
+                    day3_part2_with_rule(input, &rule),
+                    Some(0),
+                    "{count:?} {reducer:?}"
+                );
+                assert_eq!(gears(input, &rule).len(), 2);
+            }
+        }
+    }
+
+    #[test]
+    fn gear_rule_reports_overflowing_ratios() {
+        let input = b"99999.99999\n99999*99999\n99999.99999\n";
+        let rule = GearRule {
+            symbols: SymbolSet::Any,
+            count: AdjacentCount::AtLeast(2),
+            reducer: Reducer::Product,
+        };
+        assert_eq!(day3_part2_with_rule(input, &rule), None);
+        let found = gears(input, &rule);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].numbers, vec![99999; 6]);
+        assert_eq!(found[0].ratio, None);
+
+        let rule = GearRule {
+            reducer: Reducer::Sum,
+            ..rule
+        };
+        assert_eq!(day3_part2_with_rule(input, &rule), Some(6 * 99999));
+
+        // Each ratio fits, but their sum doesn't.
+        let input = b"4294967295*4294967295\n.....................\n4294967295*4294967295\n";
+        let rule = GearRule {
+            reducer: Reducer::Product,
+            ..rule
+        };
+        assert_eq!(day3_part2_with_rule(input, &rule), None);
+        assert!(gears(input, &rule).iter().all(|gear| gear.ratio.is_some()));
+    }
+
+    fn generate_grid(width: usize, height: usize, row_end: &[u8]) -> Vec<u8> {
+        let mut input = Vec::with_capacity((width + 1) * height);
+        for _ in 0..height {
+            input.resize(input.len() + width - row_end.len(), b'.');
+            input.extend_from_slice(row_end);
+            input.push(b'\n');
+        }
+        input
+    }
+
+    #[test]
+    fn chooses_small_coordinates_for_official_input() {
+        let input = utils::load_real(3);
+        assert!(Grid::new(&input).fits::<i16>());
+        let input = generate_grid(40_000, 2, b"1");
+        assert!(!Grid::new(&input).fits::<i16>());
+        assert!(Grid::new(&input).fits::<i32>());
+        assert!(Grid::new(&input).fits::<isize>());
+    }
+
+    #[test]
+    fn handles_grids_wider_than_i16() {
+        let (width, height) = (40_000, 100);
+        let input = generate_grid(width, height, b"7*.");
+        assert_eq!(input.len(), (width + 1) * height);
+        assert_eq!(day3_part1(&input), 7 * (3 * height as u32 - 2));
+        assert_eq!(
+            day3_part1_distinct(&input, &SymbolSet::Any),
+            7 * height as u32
+        );
+        assert_eq!(day3_part2(&input), 2 * 49);
+
+        let rule = GearRule::default();
+        let gears = gears(&input, &rule);
+        assert_eq!(gears.len(), 2);
+        assert_eq!(gears[1].symbol.row, height - 1);
+        assert_eq!(gears[1].symbol.column, width - 2);
+    }
+
+    #[test]
+    fn handles_grids_taller_than_i16() {
+        let height = 1_000_000;
+        let input = generate_grid(3, height, b"1*.");
+        assert_eq!(day3_part1(&input), 3 * height as u32 - 2);
+        assert_eq!(day3_part1_distinct(&input, &SymbolSet::Any), height as u32);
+        assert_eq!(day3_part2(&input), 2);
+        assert_eq!(
+            day3_part2_with_rule(
+                &input,
+                &GearRule {
+                    symbols: SymbolSet::Any,
+                    count: AdjacentCount::Exactly(3),
+                    reducer: Reducer::Sum,
+                }
+            ),
+            Some(3 * (height as u64 - 2))
+        );
+        assert_eq!(part_report(&input, &SymbolSet::Any).parts.len(), height);
+    }
+
+    #[test]
+    fn single_pass_counts_each_adjacent_number_once() {
+        assert_eq!(day3_part1_single_pass(b"*12#\n"), 12);
+        assert_eq!(day3_part1_single_pass(b".*.\n.5.\n.#.\n"), 5);
+        assert_eq!(day3_part1_single_pass(b"3.3\n.*.\n3.3\n"), 12);
+        assert_eq!(day3_part1_single_pass(b"12.\n..%\n"), 12);
+        assert_eq!(day3_part1_single_pass(b"12..\n...%\n"), 0);
+        assert_eq!(day3_part1_single_pass(b"...\n.7.\n...\n"), 0);
+        assert_eq!(day3_part1_single_pass(b"9\n"), 0);
+    }
+
+    #[test]
+    fn single_pass_handles_numbers_and_symbols_across_mask_words() {
+        for offset in 58..70 {
+            for row_end in [&b"123*"[..], b"123.", b"*123", b".123"] {
+                let mut row = vec![b'.'; offset];
+                row.extend_from_slice(row_end);
+                row.resize(130, b'.');
+                let mut symbols = vec![b'.'; 130];
+                let mut input = Vec::new();
+                for symbol in [offset - 1, offset + 4] {
+                    symbols.fill(b'.');
+                    if symbol < 130 {
+                        symbols[symbol] = b'#';
+                    }
+                    for rows in [[&row, &symbols], [&symbols, &row]] {
+                        input.clear();
+                        for r in rows {
+                            input.extend_from_slice(r);
+                            input.push(b'\n');
+                        }
+                        assert_eq!(
+                            day3_part1_single_pass(&input),
+                            day3_part1_distinct(&input, &SymbolSet::Any),
+                            "{}",
+                            String::from_utf8_lossy(&input)
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn single_pass_matches_distinct_counting_on_generated_grids() {
+        let input = generate_grid(40_000, 20, b"7*.");
+        assert_eq!(
+            day3_part1_single_pass(&input),
+            day3_part1_distinct(&input, &SymbolSet::Any)
+        );
+        let input = generate_grid(64, 20, b"7*");
+        assert_eq!(
+            day3_part1_single_pass(&input),
+            day3_part1_distinct(&input, &SymbolSet::Any)
+        );
+    }
+
+    #[test]
+    fn test_day3_part1_single_pass_example() {
+        let input = utils::load_example(3);
+        assert_eq!(day3_part1_single_pass(&input), 4361);
+    }
+
+    #[test]
+    fn test_day3_part1_single_pass_real() {
+        let input = utils::load_real(3);
+        assert_eq!(day3_part1_single_pass(&input), 539637);
+    }
+
     #[test]
     fn test_day3_part1_example() {
         let input = utils::load_example(3);
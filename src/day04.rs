@@ -1,6 +1,7 @@
 use std::{
     collections::VecDeque,
-    simd::{prelude::*, u8x16},
+    fmt::{self, Display, Write},
+    simd::{prelude::*, u8x16, u8x64},
 };
 
 use arrayvec::ArrayVec;
@@ -63,33 +64,638 @@ impl Game {
     }
 }
 
-fn games(input: &[u8]) -> impl Iterator<Item = Game> + '_ {
+fn rows(input: &[u8]) -> impl Iterator<Item = &[u8]> {
     let mut last_start = 0;
     memchr_iter(b'\n', input).map(move |idx| {
-        let game = Game::from_row(&input[last_start..idx]);
+        let row = &input[last_start..idx];
         last_start = idx + 1;
-        game
+        row
     })
 }
 
+fn game_matches(row: &[u8]) -> u32 {
+    Game::from_row(row).matches().into()
+}
+
+/// The longest compact row that [`CompactShape`] can check.
+const MAX_COMPACT_ROW: usize = 3 * u8x64::LEN;
+
+/// Where the separators of a compact row are, taken from the first row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CompactShape {
+    colon: usize,
+    bar: usize,
+    len: usize,
+    /// Bits for the columns that must be spaces: the one before each number and before the bar.
+    spaces: [u64; 3],
+    /// Bits for the columns that must be digits: the last one of each number.
+    digits: [u64; 3],
+}
+
+impl CompactShape {
+    fn new(colon: usize, bar: usize, len: usize) -> Self {
+        let mut shape = Self {
+            colon,
+            bar,
+            len,
+            spaces: [0; 3],
+            digits: [0; 3],
+        };
+        let columns = (colon + 1..bar - 1)
+            .step_by(3)
+            .chain((bar + 1..len).step_by(3));
+        for column in columns.chain([bar - 1]) {
+            shape.spaces[column / 64] |= 1 << (column % 64);
+        }
+        for column in (colon + 3..bar).step_by(3).chain((bar + 3..len).step_by(3)) {
+            shape.digits[column / 64] |= 1 << (column % 64);
+        }
+        shape
+    }
+
+    /// Whether the row at the start of `rest` can be parsed with fixed offsets: it has the same
+    /// length and separators as the first row, and every column is a space followed by one or two
+    /// digits. The bytes after the row are only read to avoid a partial load for each row.
+    fn fits(&self, rest: &[u8]) -> bool {
+        if rest.get(self.len) != Some(&b'\n') || rest[self.colon] != b':' || rest[self.bar] != b'|'
+        {
+            return false;
+        }
+        (0..self.len.div_ceil(u8x64::LEN)).all(|word| {
+            let start = word * u8x64::LEN;
+            let chunk = match rest.get(start..start + u8x64::LEN) {
+                Some(full) => u8x64::from_slice(full),
+                None => u8x64::load_or(&rest[start..], u8x64::splat(0)),
+            };
+            let spaces = chunk.simd_eq(u8x64::splat(b' ')).to_bitmask();
+            let digits = (chunk.simd_ge(u8x64::splat(b'0')) & chunk.simd_le(u8x64::splat(b'9')))
+                .to_bitmask();
+            self.spaces[word] & !spaces == 0 && self.digits[word] & !digits == 0
+        })
+    }
+}
+
+/// How the rows of the input are laid out, based on the first row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layout {
+    /// The layout used by the official inputs: at most 16 winners and 25 card numbers, all below
+    /// 100, each in a column three characters wide. Rows with the same shape as the first can be
+    /// parsed with fixed offsets; any later row that doesn't fit is parsed as a wide row.
+    Compact(CompactShape),
+    /// Any other layout, parsed by splitting on whitespace, with winners stored in a bitset.
+    Wide,
+}
+
+impl Layout {
+    fn detect(input: &[u8]) -> Self {
+        let row = &input[..memchr::memchr(b'\n', input).unwrap_or(input.len())];
+        let (Some(colon), Some(bar)) = (memchr::memchr(b':', row), memchr::memchr(b'|', row))
+        else {
+            return Layout::Wide;
+        };
+        let winners = numbers(&row[colon + 1..bar]).count();
+        let card = numbers(&row[bar + 1..]).count();
+        let below_100 = numbers(&row[colon + 1..bar])
+            .chain(numbers(&row[bar + 1..]))
+            .all(|number| matches!(number, Number::Small(value) if value < 100));
+        let fixed_columns = bar == colon + 2 + 3 * winners && row.len() == bar + 1 + 3 * card;
+        if fixed_columns && below_100 && winners <= 16 && card <= 25 && row.len() <= MAX_COMPACT_ROW
+        {
+            Layout::Compact(CompactShape::new(colon, bar, row.len()))
+        } else {
+            Layout::Wide
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    fn insert(&mut self, value: usize) {
+        if self.words.len() <= value / 64 {
+            self.words.resize(value / 64 + 1, 0);
+        }
+        self.words[value / 64] |= 1 << (value % 64);
+    }
+
+    fn remove(&mut self, value: usize) {
+        self.words[value / 64] &= !(1 << (value % 64));
+    }
+
+    fn contains(&self, value: usize) -> bool {
+        self.words
+            .get(value / 64)
+            .is_some_and(|word| word & (1 << (value % 64)) != 0)
+    }
+}
+
+/// Winners below this are kept in the bitset. Larger ones are kept in a sorted list instead, so
+/// that one huge winner can't make the bitset allocate its whole range.
+const BITSET_RANGE: usize = 1 << 16;
+
+/// A number on a wide row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Number<'a> {
+    /// A number below [`BITSET_RANGE`].
+    Small(usize),
+    /// Any larger number, as its digits without leading zeros, since it may not fit in an integer.
+    Large(&'a [u8]),
+}
+
+/// Parses whitespace-separated numbers of any length.
+fn numbers(input: &[u8]) -> impl Iterator<Item = Number<'_>> + '_ {
+    input
+        .split(|byte| byte.is_ascii_whitespace())
+        .filter(|token| !token.is_empty())
+        .map(|token| {
+            let digits = &token[token.iter().take_while(|&&digit| digit == b'0').count()..];
+            digits
+                .iter()
+                .try_fold(0usize, |acc, &digit| {
+                    acc.checked_mul(10)?.checked_add(usize::from(digit - b'0'))
+                })
+                .filter(|&value| value < BITSET_RANGE)
+                .map_or(Number::Large(digits), Number::Small)
+        })
+}
+
+/// Counts matches on rows of any layout, reusing one bitset of winners between rows.
+#[derive(Debug, Default)]
+struct WideMatcher {
+    winners: BitSet,
+    inserted: Vec<usize>,
+}
+
+impl WideMatcher {
+    fn matches(&mut self, row: &[u8]) -> u32 {
+        let colon = memchr::memchr(b':', row).unwrap();
+        let bar = memchr::memchr(b'|', row).unwrap();
+        let mut large_winners = Vec::new();
+        for winner in numbers(&row[colon + 1..bar]) {
+            match winner {
+                Number::Small(winner) => {
+                    self.winners.insert(winner);
+                    self.inserted.push(winner);
+                }
+                Number::Large(digits) => large_winners.push(digits),
+            }
+        }
+        large_winners.sort_unstable();
+        let matches = numbers(&row[bar + 1..])
+            .filter(|&number| match number {
+                Number::Small(value) => self.winners.contains(value),
+                Number::Large(digits) => large_winners.binary_search(&digits).is_ok(),
+            })
+            .count();
+        // Only clear the bits that were set, rather than the whole set.
+        for winner in self.inserted.drain(..) {
+            self.winners.remove(winner);
+        }
+        matches as u32
+    }
+}
+
+fn wide_matches(input: &[u8]) -> impl Iterator<Item = u32> + '_ {
+    let mut matcher = WideMatcher::default();
+    rows(input).map(move |row| matcher.matches(row))
+}
+
+/// Counts matches with `compact` on every row that fits `shape`, falling back to the wide parser
+/// for rows that don't.
+fn compact_matches<'a>(
+    input: &'a [u8],
+    shape: CompactShape,
+    compact: impl Fn(&[u8]) -> u32 + 'a,
+) -> impl Iterator<Item = u32> + 'a {
+    let mut wide = WideMatcher::default();
+    let mut last_start = 0;
+    memchr_iter(b'\n', input).map(move |idx| {
+        let rest = &input[last_start..];
+        let row = &input[last_start..idx];
+        last_start = idx + 1;
+        if shape.fits(rest) {
+            compact(row)
+        } else {
+            wide.matches(row)
+        }
+    })
+}
+
+/// Counts matches by parsing each row's winners and card numbers into 128-bit sets, and counting
+/// the numbers in both. This only works for the compact layout, where every number is below 100.
+/// A set can't count a number that appears on the card twice, so such rows are counted by
+/// [`Game::matches`] instead, which counts every copy.
+fn bitset_matches(row: &[u8]) -> u32 {
+    let mut winners = 0_u128;
+    let mut idx = memchr::memchr(b':', row).unwrap() + 2;
+    while row[idx] != b'|' {
+        winners |= 1 << parse_number(&row[idx..], 2);
+        idx += 3;
+    }
+    idx += 2;
+    let mut card = 0_u128;
+    let mut repeated = 0_u128;
+    while idx < row.len() {
+        let bit = 1 << parse_number(&row[idx..], 2);
+        repeated |= card & bit;
+        card |= bit;
+        idx += 3;
+    }
+    if repeated != 0 {
+        return game_matches(row);
+    }
+    (winners & card).count_ones()
+}
+
+pub fn day4_part1_bitset(input: &[u8]) -> u32 {
+    match Layout::detect(input) {
+        Layout::Compact(shape) => score(compact_matches(input, shape, bitset_matches)),
+        Layout::Wide => score(wide_matches(input)),
+    }
+}
+
+pub fn day4_part2_bitset(input: &[u8]) -> u32 {
+    match Layout::detect(input) {
+        Layout::Compact(shape) => count_scratchcards(compact_matches(input, shape, bitset_matches)),
+        Layout::Wide => count_scratchcards(wide_matches(input)),
+    }
+}
+
 pub fn day4_part1(input: &[u8]) -> u32 {
-    games(input)
-        .map(|game| {
-            let wins = game.matches();
-            if wins > 0 {
-                2_u32.pow((wins - 1).into())
-            } else {
-                0
+    match Layout::detect(input) {
+        Layout::Compact(shape) => score(compact_matches(input, shape, game_matches)),
+        Layout::Wide => score(wide_matches(input)),
+    }
+}
+
+pub fn day4_part2(input: &[u8]) -> u32 {
+    match Layout::detect(input) {
+        Layout::Compact(shape) => count_scratchcards(compact_matches(input, shape, game_matches)),
+        Layout::Wide => count_scratchcards(wide_matches(input)),
+    }
+}
+
+/// How a single card fits into the part 2 cascade. Cards are numbered from 1, in input order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardCascade {
+    pub card: usize,
+    pub matches: u32,
+    /// The number of copies of this card held at the end, including the original.
+    pub copies: u32,
+    /// Each earlier card that won copies of this card, with the number of copies it contributed.
+    pub contributors: Vec<(usize, u32)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Cascade {
+    pub cards: Vec<CardCascade>,
+}
+
+impl Cascade {
+    pub fn total(&self) -> u32 {
+        self.cards.iter().map(|card| card.copies).sum()
+    }
+
+    /// Renders the cascade as a Graphviz graph, with an edge from each card to every card it won
+    /// copies of.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph cascade {\n");
+        for card in &self.cards {
+            writeln!(
+                dot,
+                "    card{0} [label=\"Card {0}\\n{1} matches, {2} copies\"];",
+                card.card, card.matches, card.copies
+            )
+            .unwrap();
+        }
+        for card in &self.cards {
+            for (contributor, copies) in &card.contributors {
+                writeln!(
+                    dot,
+                    "    card{contributor} -> card{} [label=\"{copies}\"];",
+                    card.card
+                )
+                .unwrap();
             }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl Display for Cascade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "card | matches | copies | won from")?;
+        for card in &self.cards {
+            write!(
+                f,
+                "{:>4} | {:>7} | {:>6} |",
+                card.card, card.matches, card.copies
+            )?;
+            for (contributor, copies) in &card.contributors {
+                write!(f, " {contributor}x{copies}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Works through the part 2 cascade, keeping track of where every copy came from.
+pub fn cascade(input: &[u8]) -> Cascade {
+    let matches = match Layout::detect(input) {
+        Layout::Compact(shape) => compact_matches(input, shape, game_matches).collect::<Vec<_>>(),
+        Layout::Wide => wide_matches(input).collect(),
+    };
+    let mut cards = matches
+        .iter()
+        .enumerate()
+        .map(|(index, &matches)| CardCascade {
+            card: index + 1,
+            matches,
+            copies: 1,
+            contributors: Vec::new(),
         })
+        .collect::<Vec<_>>();
+    for index in 0..cards.len() {
+        let copies = cards[index].copies;
+        let last = cards.len().min(index + 1 + cards[index].matches as usize);
+        for won in &mut cards[index + 1..last] {
+            won.copies += copies;
+            won.contributors.push((index + 1, copies));
+        }
+    }
+    Cascade { cards }
+}
+
+/// A card parsed by [`parse_validated`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Card {
+    pub id: usize,
+    pub winners: Vec<usize>,
+    pub numbers: Vec<usize>,
+}
+
+impl Card {
+    pub fn matches(&self) -> u32 {
+        self.numbers
+            .iter()
+            .filter(|number| self.winners.contains(number))
+            .count() as u32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Separator {
+    Colon,
+    Bar,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CardProblem {
+    /// The row doesn't start with `Card <id>:`.
+    MalformedHeader,
+    MissingBar,
+    InvalidNumber,
+    /// Cards must be numbered 1, 2, 3, and so on, since part 2 relies on their order.
+    UnexpectedId {
+        expected: usize,
+        found: usize,
+    },
+    DuplicateWinner(usize),
+    DuplicateNumber(usize),
+    /// The separator is in a different column from the first row.
+    MisalignedSeparator {
+        separator: Separator,
+        expected_column: usize,
+    },
+    WinnerCount {
+        expected: usize,
+        found: usize,
+    },
+    NumberCount {
+        expected: usize,
+        found: usize,
+    },
+    /// The card would win copies of cards after the last one.
+    WinsPastLastCard {
+        matches: u32,
+        remaining_cards: usize,
+    },
+}
+
+/// A problem found by [`parse_validated`]. Lines and columns start at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardError {
+    pub line: usize,
+    pub column: usize,
+    pub problem: CardProblem,
+}
+
+impl Display for CardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: ", self.line, self.column)?;
+        match &self.problem {
+            CardProblem::MalformedHeader => write!(f, "expected `Card <id>:`"),
+            CardProblem::MissingBar => write!(f, "missing `|`"),
+            CardProblem::InvalidNumber => write!(f, "not a number"),
+            CardProblem::UnexpectedId { expected, found } => {
+                write!(f, "expected card {expected}, found card {found}")
+            }
+            CardProblem::DuplicateWinner(value) => write!(f, "duplicate winning number {value}"),
+            CardProblem::DuplicateNumber(value) => write!(f, "duplicate card number {value}"),
+            CardProblem::MisalignedSeparator {
+                separator,
+                expected_column,
+            } => write!(f, "expected {separator:?} at column {expected_column}"),
+            CardProblem::WinnerCount { expected, found } => {
+                write!(f, "expected {expected} winning numbers, found {found}")
+            }
+            CardProblem::NumberCount { expected, found } => {
+                write!(f, "expected {expected} card numbers, found {found}")
+            }
+            CardProblem::WinsPastLastCard {
+                matches,
+                remaining_cards,
+            } => write!(
+                f,
+                "{matches} matches, but only {remaining_cards} cards follow"
+            ),
+        }
+    }
+}
+
+/// Parses whitespace-separated numbers, along with the (0-based) offset of each within `input`.
+fn numbers_with_offsets(input: &[u8]) -> impl Iterator<Item = (usize, Option<usize>)> + '_ {
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        while input.get(offset)?.is_ascii_whitespace() {
+            offset += 1;
+        }
+        let start = offset;
+        while offset < input.len() && !input[offset].is_ascii_whitespace() {
+            offset += 1;
+        }
+        let token = &input[start..offset];
+        let value = token.iter().try_fold(0_usize, |acc, &digit| {
+            digit
+                .is_ascii_digit()
+                .then(|| acc * 10 + usize::from(digit - b'0'))
+        });
+        Some((start, value))
+    })
+}
+
+/// A slower, stricter parser than the one used by `day4_part1` and `day4_part2`. It checks that
+/// cards are numbered in order, that no card repeats a winning number or card number, that every
+/// row is laid out like the first, and that no card wins copies of cards past the end.
+pub fn parse_validated(input: &[u8]) -> Result<Vec<Card>, Vec<CardError>> {
+    let mut cards = Vec::new();
+    let mut errors = Vec::new();
+    // The columns of the separators, and the counts of numbers, in the first row.
+    let mut layout = None;
+
+    let input = input.strip_suffix(b"\n").unwrap_or(input);
+    for (row, line) in input.split(|&byte| byte == b'\n').zip(1..) {
+        let mut error = |column: usize, problem| {
+            errors.push(CardError {
+                line,
+                column: column + 1,
+                problem,
+            })
+        };
+
+        let Some(colon) = memchr::memchr(b':', row) else {
+            error(0, CardProblem::MalformedHeader);
+            continue;
+        };
+        let id = row
+            .strip_prefix(b"Card ")
+            .and_then(|id| numbers_with_offsets(&id[..colon - 5]).next())
+            .and_then(|(_, id)| id);
+        let Some(id) = id else {
+            error(0, CardProblem::MalformedHeader);
+            continue;
+        };
+        if id != line {
+            error(
+                0,
+                CardProblem::UnexpectedId {
+                    expected: line,
+                    found: id,
+                },
+            );
+        }
+        let Some(bar) = memchr::memchr(b'|', row) else {
+            error(row.len(), CardProblem::MissingBar);
+            continue;
+        };
+
+        let mut card = Card {
+            id,
+            winners: Vec::new(),
+            numbers: Vec::new(),
+        };
+        for (start, end, is_winners) in [(colon + 1, bar, true), (bar + 1, row.len(), false)] {
+            for (offset, value) in numbers_with_offsets(&row[start..end]) {
+                let column = start + offset;
+                let Some(value) = value else {
+                    error(column, CardProblem::InvalidNumber);
+                    continue;
+                };
+                if is_winners {
+                    if card.winners.contains(&value) {
+                        error(column, CardProblem::DuplicateWinner(value));
+                    }
+                    card.winners.push(value);
+                } else {
+                    if card.numbers.contains(&value) {
+                        error(column, CardProblem::DuplicateNumber(value));
+                    }
+                    card.numbers.push(value);
+                }
+            }
+        }
+
+        let &mut (expected_colon, expected_bar, winners, numbers) =
+            layout.get_or_insert((colon, bar, card.winners.len(), card.numbers.len()));
+        if colon != expected_colon {
+            error(
+                colon,
+                CardProblem::MisalignedSeparator {
+                    separator: Separator::Colon,
+                    expected_column: expected_colon + 1,
+                },
+            );
+        }
+        if bar != expected_bar {
+            error(
+                bar,
+                CardProblem::MisalignedSeparator {
+                    separator: Separator::Bar,
+                    expected_column: expected_bar + 1,
+                },
+            );
+        }
+        if card.winners.len() != winners {
+            error(
+                colon + 1,
+                CardProblem::WinnerCount {
+                    expected: winners,
+                    found: card.winners.len(),
+                },
+            );
+        }
+        if card.numbers.len() != numbers {
+            error(
+                bar + 1,
+                CardProblem::NumberCount {
+                    expected: numbers,
+                    found: card.numbers.len(),
+                },
+            );
+        }
+
+        cards.push((line, card));
+    }
+
+    let rows = input.split(|&byte| byte == b'\n').count();
+    for (line, card) in &cards {
+        let matches = card.matches();
+        let remaining_cards = rows - line;
+        if matches as usize > remaining_cards {
+            errors.push(CardError {
+                line: *line,
+                column: 1,
+                problem: CardProblem::WinsPastLastCard {
+                    matches,
+                    remaining_cards,
+                },
+            });
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(cards.into_iter().map(|(_, card)| card).collect())
+    } else {
+        errors.sort_by_key(|error| (error.line, error.column));
+        Err(errors)
+    }
+}
+
+fn score(matches: impl Iterator<Item = u32>) -> u32 {
+    matches
+        .map(|wins| if wins > 0 { 2_u32.pow(wins - 1) } else { 0 })
         .sum()
 }
 
-pub fn day4_part2(input: &[u8]) -> u32 {
+fn count_scratchcards(matches: impl Iterator<Item = u32>) -> u32 {
     let mut future_wins = VecDeque::<u32>::new();
-    games(input)
-        .map(|game| {
-            let win_count = game.matches();
+    matches
+        .map(|win_count| {
             let scratchcards = future_wins.pop_front().unwrap_or(0) + 1;
             if future_wins.len() < win_count as usize {
                 future_wins.resize(win_count as usize, 0);
@@ -118,7 +724,7 @@ fn parse_number(input: &[u8], count: usize) -> u8 {
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use crate::utils;
+    use crate::utils::{self, XorShift};
 
     #[test]
     fn test_parsing_number_produces_correct_number() {
@@ -135,6 +741,338 @@ pub mod tests {
         assert_eq!(parse_number(b"313", 2), 31);
     }
 
+    #[test]
+    fn detects_layout_from_first_row() {
+        assert!(matches!(
+            Layout::detect(&utils::load_example(4)),
+            Layout::Compact(_)
+        ));
+        assert!(matches!(
+            Layout::detect(&utils::load_real(4)),
+            Layout::Compact(_)
+        ));
+        assert_eq!(Layout::detect(b"Card 1: 1 2 | 3 4\n"), Layout::Wide);
+        assert_eq!(Layout::detect(b"Card 1: 10 100 | 10 11\n"), Layout::Wide);
+        let row = format!("Card 1:{} | 10\n", " 10".repeat(17));
+        assert_eq!(Layout::detect(row.as_bytes()), Layout::Wide);
+        let row = format!("Card 1:{} | 10\n", " 10".repeat(16));
+        assert!(matches!(Layout::detect(row.as_bytes()), Layout::Compact(_)));
+    }
+
+    #[test]
+    fn wide_layout_matches_compact_layout_on_real_input() {
+        let input = utils::load_real(4);
+        let compact = rows(&input).map(game_matches).collect::<Vec<_>>();
+        assert_eq!(wide_matches(&input).collect::<Vec<_>>(), compact);
+    }
+
+    /// Generates cards with many winners and numbers up to four digits wide, separated by
+    /// varying amounts of whitespace, along with the number of matches on each card.
+    fn generate_wide_cards(cards: usize) -> (Vec<u8>, Vec<u32>) {
+        let mut rng = XorShift::new(0x2545_f491_4f6c_dd1d);
+        let mut input = Vec::new();
+        let mut expected = Vec::new();
+        for card in 1..=cards {
+            let winners = (0..40).map(|_| rng.below(4000)).collect::<Vec<_>>();
+            let numbers = (0..120).map(|_| rng.below(4000)).collect::<Vec<_>>();
+            expected.push(numbers.iter().filter(|n| winners.contains(n)).count() as u32);
+            input.extend_from_slice(format!("Card {card}:").as_bytes());
+            for n in winners {
+                input.extend_from_slice(
+                    format!("{}{n}", " ".repeat(1 + rng.below(3) as usize)).as_bytes(),
+                );
+            }
+            input.extend_from_slice(b" |");
+            for n in numbers {
+                input.extend_from_slice(
+                    format!("{}{n}", " ".repeat(1 + rng.below(3) as usize)).as_bytes(),
+                );
+            }
+            input.push(b'\n');
+        }
+        (input, expected)
+    }
+
+    #[test]
+    fn handles_generated_wide_cards() {
+        let (input, expected) = generate_wide_cards(100);
+        assert_eq!(Layout::detect(&input), Layout::Wide);
+        assert_eq!(wide_matches(&input).collect::<Vec<_>>(), expected);
+        assert_eq!(day4_part1(&input), score(expected.iter().copied()));
+        assert_eq!(day4_part2(&input), count_scratchcards(expected.into_iter()));
+    }
+
+    #[test]
+    fn handles_cards_with_three_digit_numbers() {
+        let input = b"Card 1: 100 5 999 | 999 100 7\nCard 2: 1 | 1\nCard 3: 2 | 3\n";
+        assert_eq!(day4_part1(input), 2 + 1);
+        assert_eq!(day4_part2(input), 1 + 2 + 4);
+    }
+
+    #[test]
+    fn handles_cards_with_huge_numbers() {
+        let input = b"Card 1: 1000000000000000000 | 1 2\nCard 2: 123456789012345678901234567890 65536 4 | 0123456789012345678901234567890 65536 7\n";
+        assert_eq!(day4_part1(input), 2);
+        assert_eq!(day4_part2(input), 1 + 1);
+
+        let mut matcher = WideMatcher::default();
+        assert_eq!(
+            matcher.matches(b"Card 1: 4000000000 65535 | 4000000000 1"),
+            1
+        );
+        assert!(matcher.winners.words.len() <= BITSET_RANGE / 64);
+    }
+
+    #[test]
+    fn falls_back_to_wide_parsing_for_later_rows() {
+        let input = b"Card 1: 41 48 | 83 41\nCard 2: 100 48 | 100 7 1 2 3\nCard 3:  1  2 |  2  5\nCard 4:  1  2 |  2  5  6\n";
+        let shape = match Layout::detect(input) {
+            Layout::Compact(shape) => shape,
+            Layout::Wide => panic!("first row is compact"),
+        };
+        let starts = [0, 22, 51, 73];
+        let fits = starts.map(|start| shape.fits(&input[start..]));
+        assert_eq!(fits, [true, false, true, false]);
+        assert!(!shape.fits(b"Card 5: 100 4 |  2  5\n"));
+        assert_eq!(day4_part1(input), 1 + 1 + 1 + 1);
+        assert_eq!(day4_part2(input), 1 + 2 + 3 + 4);
+        assert_eq!(day4_part1_bitset(input), day4_part1(input));
+        assert_eq!(day4_part2_bitset(input), day4_part2(input));
+        assert_eq!(cascade(input).total(), day4_part2(input));
+    }
+
+    #[test]
+    fn bitset_matches_agree_with_simd_matches() {
+        for input in [utils::load_example(4), utils::load_real(4)] {
+            let simd = rows(&input).map(game_matches).collect::<Vec<_>>();
+            assert_eq!(rows(&input).map(bitset_matches).collect::<Vec<_>>(), simd);
+        }
+    }
+
+    #[test]
+    fn bitset_matches_handle_single_digit_numbers_and_extremes() {
+        let input = b"Card 1:  0  9 99 |  9  0 98 99\nCard 2:  1 | 10\n";
+        assert_eq!(
+            rows(input).map(bitset_matches).collect::<Vec<_>>(),
+            vec![3, 0]
+        );
+    }
+
+    #[test]
+    fn bitset_matches_count_repeated_card_numbers() {
+        let input = b"Card 1:  5  9 |  5  5  9  1\nCard 2:  5  5 |  5  1  2  3\nCard 3:  1  2 |  3  4  5  6\n";
+        assert_eq!(
+            rows(input).map(bitset_matches).collect::<Vec<_>>(),
+            vec![3, 1, 0]
+        );
+        assert_eq!(day4_part1_bitset(input), day4_part1(input));
+        assert_eq!(day4_part2_bitset(input), day4_part2(input));
+    }
+
+    #[test]
+    fn test_day4_bitset_real() {
+        let input = utils::load_real(4);
+        assert_eq!(day4_part1_bitset(&input), 21105);
+        assert_eq!(day4_part2_bitset(&input), 5329815);
+    }
+
+    #[test]
+    fn cascade_tracks_where_copies_came_from() {
+        let input = utils::load_example(4);
+        let cascade = cascade(&input);
+        assert_eq!(cascade.total(), 30);
+        assert_eq!(
+            cascade.cards[3],
+            CardCascade {
+                card: 4,
+                matches: 1,
+                copies: 8,
+                contributors: vec![(1, 1), (2, 2), (3, 4)],
+            }
+        );
+        assert_eq!(
+            cascade
+                .cards
+                .iter()
+                .map(|card| (card.matches, card.copies))
+                .collect::<Vec<_>>(),
+            vec![(4, 1), (2, 2), (2, 4), (1, 8), (0, 14), (0, 1)]
+        );
+    }
+
+    #[test]
+    fn cascade_ignores_wins_past_the_last_card() {
+        let input = b"Card 1: 1 2 | 1 2\nCard 2: 3 | 3\n";
+        let cascade = cascade(input);
+        assert_eq!(cascade.total(), 1 + 2);
+        assert_eq!(cascade.cards[1].contributors, vec![(1, 1)]);
+    }
+
+    #[test]
+    fn cascade_renders_as_table_and_dot() {
+        let input = b"Card 1: 1 2 | 1 5\nCard 2: 3 | 4\n";
+        let cascade = cascade(input);
+        assert_eq!(
+            cascade.to_string(),
+            "card | matches | copies | won from\n   1 |       1 |      1 |\n   2 |       0 |      2 | 1x1\n"
+        );
+        assert_eq!(
+            cascade.to_dot(),
+            "digraph cascade {\n    card1 [label=\"Card 1\\n1 matches, 1 copies\"];\n    card2 [label=\"Card 2\\n0 matches, 2 copies\"];\n    card1 -> card2 [label=\"1\"];\n}\n"
+        );
+    }
+
+    #[test]
+    fn cascade_total_matches_part2_on_real_input() {
+        let input = utils::load_real(4);
+        assert_eq!(cascade(&input).total(), day4_part2(&input));
+    }
+
+    #[test]
+    fn validated_parse_accepts_official_inputs() {
+        for input in [utils::load_example(4), utils::load_real(4)] {
+            let cards = parse_validated(&input).unwrap();
+            assert_eq!(
+                cards.iter().map(Card::matches).collect::<Vec<_>>(),
+                rows(&input).map(game_matches).collect::<Vec<_>>()
+            );
+        }
+    }
+
+    #[test]
+    fn validated_parse_reports_card_ids() {
+        let input = b"Card 1: 1 | 2\nCard 3: 1 | 2\nCard x: 1 | 2\nGame 4: 1 | 2\n";
+        let errors = parse_validated(input).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                CardError {
+                    line: 2,
+                    column: 1,
+                    problem: CardProblem::UnexpectedId {
+                        expected: 2,
+                        found: 3
+                    },
+                },
+                CardError {
+                    line: 3,
+                    column: 1,
+                    problem: CardProblem::MalformedHeader,
+                },
+                CardError {
+                    line: 4,
+                    column: 1,
+                    problem: CardProblem::MalformedHeader,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn validated_parse_reports_duplicates_with_columns() {
+        let input = b"Card 1: 41 48 41 | 83 86 83 12\nCard 2:  1  2  3 |  4  5  6  7\n";
+        let errors = parse_validated(input).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                CardError {
+                    line: 1,
+                    column: 15,
+                    problem: CardProblem::DuplicateWinner(41),
+                },
+                CardError {
+                    line: 1,
+                    column: 26,
+                    problem: CardProblem::DuplicateNumber(83),
+                },
+            ]
+        );
+        assert_eq!(errors[0].to_string(), "1:15: duplicate winning number 41");
+    }
+
+    #[test]
+    fn validated_parse_reports_inconsistent_layout() {
+        let input = b"Card 1: 1 2 | 3 4\nCard 2:  1 2 | 3 4\nCard 3: 1 | 3 4 5\nCard 4: 1 2 3 4\nCard 5: 1 2 | 3 4 5 x\n";
+        let errors = parse_validated(input).unwrap_err();
+        assert_eq!(
+            errors
+                .iter()
+                .map(|error| (error.line, error.column, error.problem.clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    2,
+                    14,
+                    CardProblem::MisalignedSeparator {
+                        separator: Separator::Bar,
+                        expected_column: 13
+                    }
+                ),
+                (
+                    3,
+                    8,
+                    CardProblem::WinnerCount {
+                        expected: 2,
+                        found: 1
+                    }
+                ),
+                (
+                    3,
+                    11,
+                    CardProblem::MisalignedSeparator {
+                        separator: Separator::Bar,
+                        expected_column: 13
+                    }
+                ),
+                (
+                    3,
+                    12,
+                    CardProblem::NumberCount {
+                        expected: 2,
+                        found: 3
+                    }
+                ),
+                (4, 16, CardProblem::MissingBar),
+                (
+                    5,
+                    14,
+                    CardProblem::NumberCount {
+                        expected: 2,
+                        found: 3
+                    }
+                ),
+                (5, 21, CardProblem::InvalidNumber),
+            ]
+        );
+    }
+
+    #[test]
+    fn validated_parse_reports_wins_past_the_last_card() {
+        let input = b"Card 1: 1 2 | 1 2\nCard 2: 1 2 | 1 3\n";
+        let errors = parse_validated(input).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                CardError {
+                    line: 1,
+                    column: 1,
+                    problem: CardProblem::WinsPastLastCard {
+                        matches: 2,
+                        remaining_cards: 1,
+                    },
+                },
+                CardError {
+                    line: 2,
+                    column: 1,
+                    problem: CardProblem::WinsPastLastCard {
+                        matches: 1,
+                        remaining_cards: 0,
+                    },
+                },
+            ]
+        );
+    }
+
     #[test]
     fn test_day4_part1_example() {
         let input = utils::load_example(4);
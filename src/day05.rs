@@ -1,36 +1,41 @@
-use std::ops::Range;
-#[derive(Debug, PartialEq, Eq)]
-struct Seed {
-    id: i64,
-    stage: u8,
-}
+use std::{
+    fmt::{self, Display},
+    mem,
+    ops::Range,
+};
 
-impl Seed {
-    fn new(id: i64) -> Self {
-        Self { id, stage: 0 }
-    }
-}
+use crate::interval_set::IntervalSet;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Default)]
 struct Seeds {
+    seeds: IntervalSet,
+    next_stage: IntervalSet,
+}
+
+/// The seed ranges as they were stored before interval sets were introduced, where ranges are
+/// never sorted or fused. Kept to compare against.
+#[derive(Debug, PartialEq, Eq, Default)]
+struct UnfusedSeeds {
     seeds: Vec<Range<i64>>,
     next_stage: Vec<Range<i64>>,
 }
 
-impl Seeds {
-    fn new() -> Self {
-        Self {
-            seeds: Vec::new(),
-            next_stage: Vec::new(),
-        }
-    }
+/// Moves every value in `source_start..source_end` by `modifier`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapLine {
+    pub source_start: i64,
+    pub source_end: i64,
+    pub modifier: i64,
 }
 
-#[derive(Debug)]
-struct MapLine {
-    source_start: i64,
-    source_end: i64,
-    modifier: i64,
+impl MapLine {
+    pub fn source(&self) -> Range<i64> {
+        self.source_start..self.source_end
+    }
+
+    pub fn destination(&self) -> Range<i64> {
+        self.source_start + self.modifier..self.source_end + self.modifier
+    }
 }
 
 fn parse_number(input: &[u8]) -> (i64, usize) {
@@ -43,25 +48,25 @@ fn parse_number(input: &[u8]) -> (i64, usize) {
     (number, i + 1)
 }
 
-fn seeds(input: &[u8]) -> (Vec<Seed>, usize) {
+fn seeds(input: &[u8]) -> (Vec<i64>, usize) {
     let mut seeds = Vec::new();
     let mut pos = 7;
     while input[pos] != b'\n' {
         let (seed, len) = parse_number(&input[pos..]);
-        seeds.push(Seed::new(seed));
+        seeds.push(seed);
         pos += len;
     }
     (seeds, pos)
 }
 
-fn seed_ranges(input: &[u8]) -> (Seeds, usize) {
-    let mut seeds = Seeds::new();
+fn seed_ranges(input: &[u8]) -> (Vec<Range<i64>>, usize) {
+    let mut seeds = Vec::new();
     let mut pos = 7;
     while input[pos] != b'\n' {
         let (start, len) = parse_number(&input[pos..]);
         pos += len;
         let (range, len) = parse_number(&input[pos..]);
-        seeds.seeds.push(start..start + range);
+        seeds.push(start..start + range);
         pos += len;
     }
     (seeds, pos)
@@ -82,7 +87,17 @@ fn parse_map_line(input: &[u8]) -> (MapLine, usize) {
 }
 
 fn apply_seedmap(map_line: MapLine, seeds: &mut Seeds) {
-    let mut to_add = None;
+    let source = map_line.source_start..map_line.source_end;
+    for moved in seeds.seeds.overlapping(source.clone()) {
+        seeds
+            .next_stage
+            .insert(moved.start + map_line.modifier..moved.end + map_line.modifier);
+    }
+    seeds.seeds.remove(source);
+}
+
+fn apply_seedmap_unfused(map_line: MapLine, seeds: &mut UnfusedSeeds) {
+    let mut to_add = Vec::new();
     seeds.seeds.retain_mut(|seed| {
         if seed.end <= map_line.source_start || seed.start >= map_line.source_end {
             true
@@ -107,16 +122,14 @@ fn apply_seedmap(map_line: MapLine, seeds: &mut Seeds) {
             seeds.next_stage.push(
                 map_line.source_start + map_line.modifier..map_line.source_end + map_line.modifier,
             );
-            to_add.replace(map_line.source_end..seed.end);
+            to_add.push(map_line.source_end..seed.end);
             seed.end = map_line.source_start;
             true
         } else {
             unreachable!("All cases should be covered")
         }
     });
-    if let Some(to_add) = to_add {
-        seeds.seeds.push(to_add);
-    }
+    seeds.seeds.append(&mut to_add);
 }
 
 fn parse_and_apply_maps_to_seed_ranges(input: &[u8], seeds: &mut Seeds) -> usize {
@@ -126,55 +139,163 @@ fn parse_and_apply_maps_to_seed_ranges(input: &[u8], seeds: &mut Seeds) -> usize
         pos += len;
         apply_seedmap(map_line, seeds);
     }
-    seeds.seeds.append(&mut seeds.next_stage);
+    seeds.seeds = seeds.seeds.union(&seeds.next_stage);
+    seeds.next_stage.clear();
 
     pos
 }
 
-fn parse_and_apply_maps(input: &[u8], stage: u8, seeds: &mut [Seed]) -> usize {
+fn parse_and_apply_maps_to_unfused_seed_ranges(input: &[u8], seeds: &mut UnfusedSeeds) -> usize {
     let mut pos = 0;
     while input.len() > pos && input[pos] != b'\n' {
         let (map_line, len) = parse_map_line(&input[pos..]);
         pos += len;
-        for seed in seeds.iter_mut() {
-            if seed.stage != stage {
-                continue;
-            }
-
-            if seed.id >= map_line.source_start && seed.id < map_line.source_end {
-                seed.id += map_line.modifier;
-                seed.stage += 1;
-            }
-        }
+        apply_seedmap_unfused(map_line, seeds);
     }
-    for seed in seeds.iter_mut() {
-        seed.stage = stage + 1;
+    seeds.seeds.append(&mut seeds.next_stage);
+
+    pos
+}
+
+/// The line whose source contains `value`, if any, from non-overlapping lines sorted by source.
+fn line_containing(lines: &[MapLine], value: i64) -> Option<&MapLine> {
+    let index = lines.partition_point(|line| line.source_end <= value);
+    lines.get(index).filter(|line| line.source_start <= value)
+}
+
+/// Parses the map lines of one stage into `lines`, in file order.
+fn parse_stage(input: &[u8], lines: &mut Vec<MapLine>) -> usize {
+    lines.clear();
+    let mut pos = 0;
+    while input.len() > pos && input[pos] != b'\n' {
+        let (map_line, len) = parse_map_line(&input[pos..]);
+        pos += len;
+        lines.push(map_line);
     }
 
     pos
 }
 
+/// Trims `lines`, given in file order, so that no two sources overlap, keeping the part of each
+/// source that no earlier line covers.
+fn first_line_wins(lines: Vec<MapLine>) -> Vec<MapLine> {
+    let mut covered = IntervalSet::new();
+    let mut trimmed = Vec::with_capacity(lines.len());
+    for line in lines {
+        let uncovered = IntervalSet::from(line.source()).difference(&covered);
+        trimmed.extend(uncovered.ranges().iter().map(|range| MapLine {
+            source_start: range.start,
+            source_end: range.end,
+            modifier: line.modifier,
+        }));
+        covered.insert(line.source());
+    }
+    trimmed
+}
+
+/// Sorts the lines parsed from `stage` by source for [`line_containing`], trimming them first if
+/// they overlap.
+fn sort_stage(stage: &[u8], lines: &mut Vec<MapLine>) {
+    lines.sort_unstable_by_key(|line| line.source_start);
+    if lines
+        .windows(2)
+        .any(|pair| pair[0].source_end > pair[1].source_start)
+    {
+        // Sorting lost the file order that decides which line wins, so parse the stage again.
+        parse_stage(stage, lines);
+        *lines = first_line_wins(mem::take(lines));
+        lines.sort_unstable_by_key(|line| line.source_start);
+    }
+}
+
+/// Below this many seeds, scanning each stage's lines in file order is cheaper than sorting them.
+const SORTED_STAGE_SEEDS: usize = 64;
+
 pub fn day5_part1(input: &[u8]) -> u64 {
+    lowest_location(input, SORTED_STAGE_SEEDS)
+}
+
+/// `day5_part1` always sorting each stage's lines, however few seeds there are, for comparison.
+pub fn day5_part1_sorted(input: &[u8]) -> u64 {
+    lowest_location(input, 0)
+}
+
+/// Maps every seed through the stages, sorting the lines of each stage once there are at least
+/// `sorted_from` seeds.
+fn lowest_location(input: &[u8], sorted_from: usize) -> u64 {
     let (mut seeds, mut pos) = seeds(input);
-    let mut stage = 0;
+    let mut lines = Vec::new();
     while input.len() > pos && input[pos] == b'\n' {
         pos += 1;
         pos += memchr::memchr(b'\n', &input[pos..]).unwrap() + 1;
-        pos += parse_and_apply_maps(&input[pos..], stage, &mut seeds);
-        stage += 1;
+        let stage = &input[pos..];
+        pos += parse_stage(stage, &mut lines);
+        if seeds.len() < sorted_from {
+            for seed in seeds.iter_mut() {
+                if let Some(line) = lines.iter().find(|line| line.source().contains(seed)) {
+                    *seed += line.modifier;
+                }
+            }
+        } else {
+            sort_stage(stage, &mut lines);
+            for seed in seeds.iter_mut() {
+                if let Some(line) = line_containing(&lines, *seed) {
+                    *seed += line.modifier;
+                }
+            }
+        }
     }
 
-    seeds.into_iter().map(|seed| seed.id).min().unwrap() as u64
+    seeds.into_iter().min().unwrap() as u64
 }
 
+/// Below this many seed ranges, mapping them as they are is cheaper than keeping them fused.
+const FUSED_SEED_RANGES: usize = 256;
+
 pub fn day5_part2(input: &[u8]) -> u64 {
-    let (mut seeds, mut pos) = seed_ranges(input);
-    while input.len() > pos && input[pos] == b'\n' {
+    let (ranges, mut pos) = seed_ranges(input);
+    let more_stages = |pos: usize| input.len() > pos && input[pos] == b'\n';
+    let mut unfused = UnfusedSeeds {
+        seeds: ranges,
+        next_stage: Vec::new(),
+    };
+    while unfused.seeds.len() < FUSED_SEED_RANGES && more_stages(pos) {
+        pos += 1;
+        pos += memchr::memchr(b'\n', &input[pos..]).unwrap() + 1;
+        pos += parse_and_apply_maps_to_unfused_seed_ranges(&input[pos..], &mut unfused);
+    }
+    if !more_stages(pos) {
+        let min = unfused.seeds.into_iter().map(|seed| seed.start).min();
+        return min.unwrap().try_into().unwrap();
+    }
+
+    // Splitting can multiply the ranges from stage to stage, so fuse them once there are many.
+    let mut seeds = Seeds {
+        seeds: unfused.seeds.into_iter().collect(),
+        next_stage: IntervalSet::new(),
+    };
+    while more_stages(pos) {
         pos += 1;
         pos += memchr::memchr(b'\n', &input[pos..]).unwrap() + 1;
         pos += parse_and_apply_maps_to_seed_ranges(&input[pos..], &mut seeds);
     }
 
+    seeds.seeds.min().unwrap().try_into().unwrap()
+}
+
+/// `day5_part2` without fusing seed ranges, for comparison.
+pub fn day5_part2_unfused(input: &[u8]) -> u64 {
+    let (ranges, mut pos) = seed_ranges(input);
+    let mut seeds = UnfusedSeeds {
+        seeds: ranges,
+        next_stage: Vec::new(),
+    };
+    while input.len() > pos && input[pos] == b'\n' {
+        pos += 1;
+        pos += memchr::memchr(b'\n', &input[pos..]).unwrap() + 1;
+        pos += parse_and_apply_maps_to_unfused_seed_ranges(&input[pos..], &mut seeds);
+    }
+
     seeds
         .seeds
         .into_iter()
@@ -185,37 +306,602 @@ pub fn day5_part2(input: &[u8]) -> u64 {
         .unwrap()
 }
 
+/// A map made of non-overlapping `MapLine`s sorted by source. Values outside every line map to
+/// themselves.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PiecewiseMap {
+    lines: Vec<MapLine>,
+}
+
+impl PiecewiseMap {
+    /// Builds a map from lines in file order. Where two lines' sources overlap, the earlier line
+    /// wins, as it does when seeds are mapped line by line. Lines that don't move anything are
+    /// dropped, and touching lines with the same modifier are merged.
+    pub fn new(lines: Vec<MapLine>) -> Self {
+        let mut lines = first_line_wins(lines);
+        lines.retain(|line| line.modifier != 0);
+        lines.sort_unstable_by_key(|line| line.source_start);
+        let mut merged: Vec<MapLine> = Vec::with_capacity(lines.len());
+        for line in lines {
+            match merged.last_mut() {
+                Some(last)
+                    if last.source_end == line.source_start && last.modifier == line.modifier =>
+                {
+                    last.source_end = line.source_end;
+                }
+                _ => merged.push(line),
+            }
+        }
+        Self { lines: merged }
+    }
+
+    pub fn lines(&self) -> &[MapLine] {
+        &self.lines
+    }
+
+    pub fn get(&self, value: i64) -> i64 {
+        value + line_containing(&self.lines, value).map_or(0, |line| line.modifier)
+    }
+
+    /// Splits `range` into consecutive pieces, each paired with the modifier applied to it.
+    /// Pieces not covered by a line have a modifier of zero.
+    pub fn segments(&self, range: Range<i64>) -> impl Iterator<Item = (Range<i64>, i64)> + '_ {
+        let first = self
+            .lines
+            .partition_point(|line| line.source_end <= range.start);
+        let mut start = range.start;
+        let mut lines = self.lines[first..].iter().peekable();
+        std::iter::from_fn(move || {
+            if start >= range.end {
+                return None;
+            }
+            let segment = match lines.peek() {
+                Some(line) if line.source_start <= start => {
+                    let line = lines.next().unwrap();
+                    (start..line.source_end.min(range.end), line.modifier)
+                }
+                Some(line) => (start..line.source_start.min(range.end), 0),
+                None => (start..range.end, 0),
+            };
+            start = segment.0.end;
+            Some(segment)
+        })
+    }
+
+    /// Maps every value in `range`, giving the destination ranges in source order.
+    pub fn map_range(&self, range: Range<i64>) -> impl Iterator<Item = Range<i64>> + '_ {
+        self.segments(range)
+            .map(|(segment, modifier)| segment.start + modifier..segment.end + modifier)
+    }
+
+    /// The map that applies `self` and then `next`.
+    pub fn then(&self, next: &PiecewiseMap) -> PiecewiseMap {
+        let mut lines = Vec::new();
+        for line in &self.lines {
+            for (segment, modifier) in next.segments(line.destination()) {
+                lines.push(MapLine {
+                    source_start: segment.start - line.modifier,
+                    source_end: segment.end - line.modifier,
+                    modifier: line.modifier + modifier,
+                });
+            }
+        }
+        // Values that `self` leaves alone go straight into `next`.
+        let moved = self
+            .lines
+            .iter()
+            .map(MapLine::source)
+            .collect::<IntervalSet>();
+        for line in &next.lines {
+            let untouched = IntervalSet::from(line.source()).difference(&moved);
+            lines.extend(untouched.ranges().iter().map(|range| MapLine {
+                source_start: range.start,
+                source_end: range.end,
+                modifier: line.modifier,
+            }));
+        }
+        PiecewiseMap::new(lines)
+    }
+
+    /// Every value that the map sends into `range`.
+    pub fn preimages(&self, range: Range<i64>) -> IntervalSet {
+        let moved = self
+            .lines
+            .iter()
+            .map(MapLine::source)
+            .collect::<IntervalSet>();
+        let mut preimages = IntervalSet::from(range.clone()).difference(&moved);
+        for line in &self.lines {
+            let destination = line.destination();
+            let hit = destination.start.max(range.start)..destination.end.min(range.end);
+            preimages.insert(hit.start - line.modifier..hit.end - line.modifier);
+        }
+        preimages
+    }
+}
+
+impl Display for PiecewiseMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            let destination = line.destination();
+            writeln!(
+                f,
+                "{}..{} -> {}..{} ({:+})",
+                line.source_start,
+                line.source_end,
+                destination.start,
+                destination.end,
+                line.modifier
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// One `X-to-Y map:` block of the almanac.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stage {
+    pub source: String,
+    pub destination: String,
+    pub map: PiecewiseMap,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Almanac {
+    pub seeds: Vec<i64>,
+    pub stages: Vec<Stage>,
+}
+
+impl Almanac {
+    pub fn parse(input: &[u8]) -> Self {
+        let text = std::str::from_utf8(input).unwrap();
+        let mut blocks = text.split("\n\n");
+        let seeds = blocks
+            .next()
+            .unwrap()
+            .strip_prefix("seeds:")
+            .unwrap()
+            .split_ascii_whitespace()
+            .map(|seed| seed.parse().unwrap())
+            .collect();
+        let stages = blocks
+            .filter(|block| !block.trim().is_empty())
+            .map(|block| {
+                let mut lines = block.lines();
+                let header = lines.next().unwrap().strip_suffix(" map:").unwrap();
+                let (source, destination) = header.split_once("-to-").unwrap();
+                let lines = lines
+                    .map(|line| {
+                        let mut numbers = line
+                            .split_ascii_whitespace()
+                            .map(|n| n.parse::<i64>().unwrap());
+                        let (destination, source, size) = (
+                            numbers.next().unwrap(),
+                            numbers.next().unwrap(),
+                            numbers.next().unwrap(),
+                        );
+                        MapLine {
+                            source_start: source,
+                            source_end: source + size,
+                            modifier: destination - source,
+                        }
+                    })
+                    .collect();
+                Stage {
+                    source: source.to_owned(),
+                    destination: destination.to_owned(),
+                    map: PiecewiseMap::new(lines),
+                }
+            })
+            .collect();
+        Self { seeds, stages }
+    }
+
+    /// The seeds line read as `start length` pairs.
+    pub fn seed_ranges(&self) -> impl Iterator<Item = Range<i64>> + '_ {
+        self.seeds
+            .chunks_exact(2)
+            .map(|pair| pair[0]..pair[0] + pair[1])
+    }
+
+    /// All stages on the way from seed to location composed into a single map.
+    pub fn compose(&self) -> Result<PiecewiseMap, ChainError> {
+        self.map_between("seed", "location")
+    }
+
+    /// Every category named in a map header, in order of first appearance.
+    pub fn categories(&self) -> Vec<&str> {
+        let mut categories = Vec::new();
+        for stage in &self.stages {
+            for category in [stage.source.as_str(), stage.destination.as_str()] {
+                if !categories.contains(&category) {
+                    categories.push(category);
+                }
+            }
+        }
+        categories
+    }
+
+    /// The stages that lead from `from` to `to`. There must be exactly one such chain.
+    pub fn chain(&self, from: &str, to: &str) -> Result<Vec<&Stage>, ChainError> {
+        let categories = self.categories();
+        for category in [from, to] {
+            if !categories.contains(&category) {
+                return Err(ChainError::UnknownCategory(category.to_owned()));
+            }
+        }
+
+        let mut found = Vec::new();
+        let mut path = Vec::new();
+        self.find_chains(from, to, &mut path, &mut found);
+        match found.len() {
+            0 => Err(ChainError::Broken {
+                from: from.to_owned(),
+                to: to.to_owned(),
+            }),
+            1 => Ok(found.pop().unwrap()),
+            _ => Err(ChainError::Ambiguous {
+                from: from.to_owned(),
+                to: to.to_owned(),
+            }),
+        }
+    }
+
+    /// Depth-first search for chains from `from` to `to`, stopping once two have been found.
+    fn find_chains<'a>(
+        &'a self,
+        from: &str,
+        to: &str,
+        path: &mut Vec<&'a Stage>,
+        found: &mut Vec<Vec<&'a Stage>>,
+    ) {
+        if from == to {
+            found.push(path.clone());
+            return;
+        }
+        for stage in self.stages.iter().filter(|stage| stage.source == from) {
+            let revisits = stage.destination == path.first().map_or(from, |first| &first.source)
+                || path
+                    .iter()
+                    .any(|step| step.destination == stage.destination);
+            if found.len() > 1 || revisits {
+                continue;
+            }
+            path.push(stage);
+            self.find_chains(&stage.destination, to, path, found);
+            path.pop();
+        }
+    }
+
+    /// The stages from `from` to `to` composed into a single map.
+    pub fn map_between(&self, from: &str, to: &str) -> Result<PiecewiseMap, ChainError> {
+        Ok(self
+            .chain(from, to)?
+            .into_iter()
+            .fold(PiecewiseMap::default(), |map, stage| map.then(&stage.map)))
+    }
+
+    /// Converts a `from` value into a `to` value.
+    pub fn convert(&self, from: &str, to: &str, value: i64) -> Result<i64, ChainError> {
+        Ok(self
+            .chain(from, to)?
+            .into_iter()
+            .fold(value, |value, stage| stage.map.get(value)))
+    }
+
+    /// Converts a range of `from` values into the `to` values they end up at.
+    pub fn convert_range(
+        &self,
+        from: &str,
+        to: &str,
+        range: Range<i64>,
+    ) -> Result<IntervalSet, ChainError> {
+        Ok(self
+            .chain(from, to)?
+            .into_iter()
+            .fold(IntervalSet::from(range), |values, stage| {
+                values
+                    .ranges()
+                    .iter()
+                    .flat_map(|range| stage.map.map_range(range.clone()))
+                    .collect()
+            }))
+    }
+
+    /// Every `from` value that ends up in the `to` range, found by walking the chain backwards.
+    pub fn unconvert_range(
+        &self,
+        from: &str,
+        to: &str,
+        range: Range<i64>,
+    ) -> Result<IntervalSet, ChainError> {
+        Ok(self.chain(from, to)?.into_iter().rev().fold(
+            IntervalSet::from(range),
+            |values, stage| {
+                values
+                    .ranges()
+                    .iter()
+                    .flat_map(|range| stage.map.preimages(range.clone()).ranges().to_vec())
+                    .collect()
+            },
+        ))
+    }
+
+    /// Every seed whose location falls in `locations`.
+    pub fn seeds_for_locations(&self, locations: Range<i64>) -> Result<IntervalSet, ChainError> {
+        self.unconvert_range("seed", "location", locations)
+    }
+
+    /// The smallest seed that ends up at `location`, if any does.
+    pub fn seed_for_location(&self, location: i64) -> Result<Option<i64>, ChainError> {
+        Ok(self.seeds_for_locations(location..location + 1)?.min())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainError {
+    UnknownCategory(String),
+    /// No chain of maps leads between the two categories.
+    Broken {
+        from: String,
+        to: String,
+    },
+    /// More than one chain of maps leads between the two categories.
+    Ambiguous {
+        from: String,
+        to: String,
+    },
+}
+
+impl Display for ChainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainError::UnknownCategory(category) => write!(f, "no map mentions {category:?}"),
+            ChainError::Broken { from, to } => {
+                write!(f, "no chain of maps leads from {from} to {to}")
+            }
+            ChainError::Ambiguous { from, to } => {
+                write!(f, "more than one chain of maps leads from {from} to {to}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ChainError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StageProblem {
+    /// The line isn't three numbers.
+    MalformedLine,
+    /// The line's source overlaps an earlier line's, so the result depends on line order.
+    OverlappingSource {
+        other_line: usize,
+        overlap: Range<i64>,
+    },
+    /// The line sends values to the same place as an earlier line.
+    CollidingDestination {
+        other_line: usize,
+        overlap: Range<i64>,
+    },
+    /// Values between the stage's lines that it leaves unchanged. Reported against the header.
+    IdentityGap(Range<i64>),
+}
+
+/// Something found by [`validate`] in one stage of an almanac. Lines start at 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlmanacIssue {
+    pub line: usize,
+    pub stage: String,
+    pub problem: StageProblem,
+}
+
+impl AlmanacIssue {
+    /// Whether the issue makes the stage's result wrong or order dependent. Identity gaps are
+    /// allowed by the puzzle, so they aren't errors.
+    pub fn is_error(&self) -> bool {
+        !matches!(self.problem, StageProblem::IdentityGap(_))
+    }
+}
+
+impl Display for AlmanacIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: ", self.line, self.stage)?;
+        match &self.problem {
+            StageProblem::MalformedLine => write!(f, "expected `<destination> <source> <length>`"),
+            StageProblem::OverlappingSource {
+                other_line,
+                overlap,
+            } => write!(
+                f,
+                "source {}..{} overlaps line {other_line}",
+                overlap.start, overlap.end
+            ),
+            StageProblem::CollidingDestination {
+                other_line,
+                overlap,
+            } => write!(
+                f,
+                "destination {}..{} collides with line {other_line}",
+                overlap.start, overlap.end
+            ),
+            StageProblem::IdentityGap(gap) => {
+                write!(f, "{}..{} is not mapped", gap.start, gap.end)
+            }
+        }
+    }
+}
+
+/// Reports overlapping pairs of `ranges`, each tagged with its line, against the later line.
+fn overlaps(
+    mut ranges: Vec<(usize, Range<i64>)>,
+) -> impl Iterator<Item = (usize, usize, Range<i64>)> {
+    ranges.sort_unstable_by_key(|(_, range)| range.start);
+    let mut active: Vec<(usize, Range<i64>)> = Vec::new();
+    let mut found = Vec::new();
+    for (line, range) in ranges {
+        active.retain(|(_, other)| other.end > range.start);
+        for (other_line, other) in &active {
+            let overlap = range.start..other.end.min(range.end);
+            found.push((line.max(*other_line), line.min(*other_line), overlap));
+        }
+        active.push((line, range));
+    }
+    found.into_iter()
+}
+
+/// The lines of one stage, each with its line number, as read by [`validate`].
+struct NumberedStage<'a> {
+    name: &'a str,
+    header_line: usize,
+    lines: Vec<(usize, MapLine)>,
+}
+
+fn validate_stage(stage: NumberedStage) -> Vec<AlmanacIssue> {
+    let NumberedStage {
+        name,
+        header_line,
+        lines,
+    } = stage;
+    let issue = |line, problem| AlmanacIssue {
+        line,
+        stage: name.to_owned(),
+        problem,
+    };
+    let mut issues = Vec::new();
+    let sources = lines
+        .iter()
+        .map(|(line, map_line)| (*line, map_line.source()));
+    for (line, other_line, overlap) in overlaps(sources.clone().collect()) {
+        issues.push(issue(
+            line,
+            StageProblem::OverlappingSource {
+                other_line,
+                overlap,
+            },
+        ));
+    }
+    let destinations = lines
+        .iter()
+        .map(|(line, map_line)| (*line, map_line.destination()));
+    for (line, other_line, overlap) in overlaps(destinations.collect()) {
+        issues.push(issue(
+            line,
+            StageProblem::CollidingDestination {
+                other_line,
+                overlap,
+            },
+        ));
+    }
+    let covered = sources.map(|(_, range)| range).collect::<IntervalSet>();
+    for pair in covered.ranges().windows(2) {
+        issues.push(issue(
+            header_line,
+            StageProblem::IdentityGap(pair[0].end..pair[1].start),
+        ));
+    }
+    issues
+}
+
+/// Checks every stage of an almanac for map lines that overlap, collide or leave gaps, without
+/// assuming the almanac is well formed.
+pub fn validate(input: &[u8]) -> Vec<AlmanacIssue> {
+    let text = String::from_utf8_lossy(input);
+    let mut issues = Vec::new();
+    let mut stage: Option<NumberedStage> = None;
+    for (index, text_line) in text.lines().enumerate() {
+        let line = index + 1;
+        if let Some(name) = text_line.strip_suffix(" map:") {
+            let next = NumberedStage {
+                name,
+                header_line: line,
+                lines: Vec::new(),
+            };
+            if let Some(finished) = stage.replace(next) {
+                issues.extend(validate_stage(finished));
+            }
+            continue;
+        }
+        let Some(NumberedStage { name, lines, .. }) = stage.as_mut() else {
+            continue;
+        };
+        if text_line.trim().is_empty() {
+            continue;
+        }
+        let numbers = text_line
+            .split_ascii_whitespace()
+            .map(|number| number.parse::<i64>().ok())
+            .collect::<Option<Vec<_>>>();
+        match numbers.as_deref() {
+            Some(&[destination, source, size]) => lines.push((
+                line,
+                MapLine {
+                    source_start: source,
+                    source_end: source + size,
+                    modifier: destination - source,
+                },
+            )),
+            _ => issues.push(AlmanacIssue {
+                line,
+                stage: name.to_string(),
+                problem: StageProblem::MalformedLine,
+            }),
+        }
+    }
+    if let Some(finished) = stage {
+        issues.extend(validate_stage(finished));
+    }
+    issues.sort_by_key(|issue| issue.line);
+    issues
+}
+
+pub fn day5_part1_composed(input: &[u8]) -> u64 {
+    let almanac = Almanac::parse(input);
+    let map = almanac.compose().unwrap();
+    almanac
+        .seeds
+        .iter()
+        .map(|&seed| map.get(seed))
+        .min()
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
+pub fn day5_part2_composed(input: &[u8]) -> u64 {
+    let almanac = Almanac::parse(input);
+    let map = almanac.compose().unwrap();
+    almanac
+        .seed_ranges()
+        .flat_map(|range| map.map_range(range))
+        .map(|range| range.start)
+        .min()
+        .unwrap()
+        .try_into()
+        .unwrap()
+}
+
 #[cfg(test)]
 pub mod tests {
     #![allow(clippy::single_range_in_vec_init)]
 
     use super::*;
-    use crate::utils;
+    use crate::utils::{self, XorShift};
 
     #[test]
     fn test_finds_seeds() {
         let input = utils::load_example(5);
-        assert_eq!(
-            seeds(&input).0,
-            vec![Seed::new(79), Seed::new(14), Seed::new(55), Seed::new(13)]
-        );
+        assert_eq!(seeds(&input).0, vec![79, 14, 55, 13]);
     }
 
     #[test]
     fn test_finds_seed_ranges() {
         let input = utils::load_example(5);
-        assert_eq!(
-            seed_ranges(&input).0,
-            Seeds {
-                seeds: vec![79..93, 55..68],
-                next_stage: Vec::new(),
-            }
-        );
+        assert_eq!(seed_ranges(&input).0, vec![79..93, 55..68]);
     }
 
     #[test]
-    fn test_applies_map_to_out_of_range_seeds() {
-        let mut seeds = Seeds {
+    fn test_applies_unfused_map_to_out_of_range_seeds() {
+        let mut seeds = UnfusedSeeds {
             seeds: vec![79..93, 55..68],
             next_stage: Vec::new(),
         };
@@ -225,10 +911,10 @@ pub mod tests {
             modifier: 99,
         };
 
-        apply_seedmap(map_line, &mut seeds);
+        apply_seedmap_unfused(map_line, &mut seeds);
         assert_eq!(
             seeds,
-            Seeds {
+            UnfusedSeeds {
                 seeds: vec![79..93, 55..68],
                 next_stage: Vec::new(),
             }
@@ -236,8 +922,8 @@ pub mod tests {
     }
 
     #[test]
-    fn test_applies_map_to_seeds_fully_within_range() {
-        let mut seeds = Seeds {
+    fn test_applies_unfused_map_to_seeds_fully_within_range() {
+        let mut seeds = UnfusedSeeds {
             seeds: vec![79..88, 55..100],
             next_stage: Vec::new(),
         };
@@ -247,10 +933,10 @@ pub mod tests {
             modifier: 10,
         };
 
-        apply_seedmap(map_line, &mut seeds);
+        apply_seedmap_unfused(map_line, &mut seeds);
         assert_eq!(
             seeds,
-            Seeds {
+            UnfusedSeeds {
                 seeds: vec![],
                 next_stage: vec![89..98, 65..110],
             }
@@ -258,8 +944,8 @@ pub mod tests {
     }
 
     #[test]
-    fn test_applies_map_to_seeds_overlapping_initial_boundary_of_map() {
-        let mut seeds = Seeds {
+    fn test_applies_unfused_map_to_seeds_overlapping_initial_boundary_of_map() {
+        let mut seeds = UnfusedSeeds {
             seeds: vec![10..30, 10..50],
             next_stage: Vec::new(),
         };
@@ -269,10 +955,10 @@ pub mod tests {
             modifier: 10,
         };
 
-        apply_seedmap(map_line, &mut seeds);
+        apply_seedmap_unfused(map_line, &mut seeds);
         assert_eq!(
             seeds,
-            Seeds {
+            UnfusedSeeds {
                 seeds: vec![10..25, 10..25],
                 next_stage: vec![35..40, 35..60],
             }
@@ -280,8 +966,8 @@ pub mod tests {
     }
 
     #[test]
-    fn test_applies_map_to_seeds_overlapping_final_boundary_of_map() {
-        let mut seeds = Seeds {
+    fn test_applies_unfused_map_to_seeds_overlapping_final_boundary_of_map() {
+        let mut seeds = UnfusedSeeds {
             seeds: vec![40..60, 20..60],
             next_stage: Vec::new(),
         };
@@ -291,10 +977,10 @@ pub mod tests {
             modifier: 5,
         };
 
-        apply_seedmap(map_line, &mut seeds);
+        apply_seedmap_unfused(map_line, &mut seeds);
         assert_eq!(
             seeds,
-            Seeds {
+            UnfusedSeeds {
                 seeds: vec![50..60, 50..60],
                 next_stage: vec![45..55, 25..55],
             }
@@ -302,8 +988,8 @@ pub mod tests {
     }
 
     #[test]
-    fn test_applies_map_to_seeds_overlapping_both_boundaries_of_map() {
-        let mut seeds = Seeds {
+    fn test_applies_unfused_map_to_seeds_overlapping_both_boundaries_of_map() {
+        let mut seeds = UnfusedSeeds {
             seeds: vec![10..60],
             next_stage: Vec::new(),
         };
@@ -313,16 +999,460 @@ pub mod tests {
             modifier: 5,
         };
 
-        apply_seedmap(map_line, &mut seeds);
+        apply_seedmap_unfused(map_line, &mut seeds);
         assert_eq!(
             seeds,
-            Seeds {
+            UnfusedSeeds {
                 seeds: vec![10..20, 50..60],
                 next_stage: vec![25..55],
             }
         );
     }
 
+    fn fused(ranges: &[Range<i64>]) -> IntervalSet {
+        ranges.iter().cloned().collect()
+    }
+
+    #[test]
+    fn test_applies_map_to_seeds_fully_within_range() {
+        let mut seeds = Seeds {
+            seeds: fused(&[79..88, 55..100]),
+            next_stage: IntervalSet::new(),
+        };
+        let map_line = MapLine {
+            source_start: 55,
+            source_end: 100,
+            modifier: 10,
+        };
+
+        apply_seedmap(map_line, &mut seeds);
+        assert_eq!(
+            seeds,
+            Seeds {
+                seeds: IntervalSet::new(),
+                next_stage: fused(&[65..110]),
+            }
+        );
+    }
+
+    #[test]
+    fn test_applies_map_to_seeds_overlapping_both_boundaries_of_map() {
+        let mut seeds = Seeds {
+            seeds: fused(&[10..60, 70..80]),
+            next_stage: fused(&[0..5]),
+        };
+        let map_line = MapLine {
+            source_start: 20,
+            source_end: 50,
+            modifier: -20,
+        };
+
+        apply_seedmap(map_line, &mut seeds);
+        assert_eq!(
+            seeds,
+            Seeds {
+                seeds: fused(&[10..20, 50..60, 70..80]),
+                next_stage: fused(&[0..30]),
+            }
+        );
+    }
+
+    #[test]
+    fn test_applies_map_to_seeds_spanning_several_ranges() {
+        let mut seeds = Seeds {
+            seeds: fused(&[10..20, 30..40, 50..60]),
+            next_stage: IntervalSet::new(),
+        };
+        let map_line = MapLine {
+            source_start: 15,
+            source_end: 55,
+            modifier: 100,
+        };
+
+        apply_seedmap(map_line, &mut seeds);
+        assert_eq!(
+            seeds,
+            Seeds {
+                seeds: fused(&[10..15, 55..60]),
+                next_stage: fused(&[115..120, 130..140, 150..155]),
+            }
+        );
+    }
+
+    #[test]
+    fn test_fused_and_unfused_maps_cover_the_same_seeds() {
+        let mut rng = XorShift::new(0x9e37_79b9_7f4a_7c15);
+        let mut random = |limit: i64| rng.below(limit as u64) as i64;
+        for _ in 0..500 {
+            let ranges = (0..random(8) + 1)
+                .map(|_| {
+                    let start = random(200);
+                    start..start + random(50) + 1
+                })
+                .collect::<Vec<_>>();
+            let mut unfused = UnfusedSeeds {
+                seeds: ranges.clone(),
+                next_stage: Vec::new(),
+            };
+            let mut fused = Seeds {
+                seeds: ranges.into_iter().collect(),
+                next_stage: IntervalSet::new(),
+            };
+
+            // Map lines within a stage never overlap, so cut the source space into pieces and
+            // map some of them.
+            for _ in 0..4 {
+                let mut start = random(20);
+                while start < 260 {
+                    let end = start + random(40) + 1;
+                    if random(3) != 0 {
+                        let modifier = random(200) - 100;
+                        let map_line = || MapLine {
+                            source_start: start,
+                            source_end: end,
+                            modifier,
+                        };
+                        apply_seedmap_unfused(map_line(), &mut unfused);
+                        apply_seedmap(map_line(), &mut fused);
+                    }
+                    start = end + random(10);
+                }
+                unfused.seeds.append(&mut unfused.next_stage);
+                fused.seeds = fused.seeds.union(&fused.next_stage);
+                fused.next_stage.clear();
+
+                assert_eq!(
+                    unfused.seeds.iter().cloned().collect::<IntervalSet>(),
+                    fused.seeds
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_day5_part2_unfused_matches_fused() {
+        let input = utils::load_example(5);
+        assert_eq!(day5_part2_unfused(&input), 46);
+        let input = utils::load_real(5);
+        assert_eq!(day5_part2_unfused(&input), day5_part2(&input));
+    }
+
+    #[test]
+    fn test_day5_part2_fuses_once_ranges_multiply() {
+        // Each of the 100 seed ranges is split in three by the first stage, so the second stage
+        // maps fused ranges.
+        let mut input = String::from("seeds:");
+        for i in 0..100 {
+            input += &format!(" {} 10", 1000 + i * 100);
+        }
+        input += "\n\nseed-to-soil map:\n";
+        for i in 0..100 {
+            input += &format!("{} {} 3\n", 50 + i * 100, 1005 + i * 100);
+        }
+        input += "\nsoil-to-location map:\n1 1000 20000\n";
+        assert_eq!(day5_part2(input.as_bytes()), 1);
+        assert_eq!(day5_part2_unfused(input.as_bytes()), 1);
+    }
+
+    fn line(source_start: i64, source_end: i64, modifier: i64) -> MapLine {
+        MapLine {
+            source_start,
+            source_end,
+            modifier,
+        }
+    }
+
+    #[test]
+    fn test_piecewise_map_sorts_and_merges_lines() {
+        let map = PiecewiseMap::new(vec![
+            line(20, 30, 5),
+            line(0, 10, 0),
+            line(10, 20, 5),
+            line(40, 50, -1),
+        ]);
+        assert_eq!(map.lines(), &[line(10, 30, 5), line(40, 50, -1)]);
+        assert_eq!(map.get(9), 9);
+        assert_eq!(map.get(10), 15);
+        assert_eq!(map.get(29), 34);
+        assert_eq!(map.get(30), 30);
+        assert_eq!(map.get(45), 44);
+        assert_eq!(
+            map.map_range(0..45).collect::<Vec<_>>(),
+            vec![0..10, 15..35, 30..40, 39..44]
+        );
+    }
+
+    #[test]
+    fn test_earlier_lines_win_where_sources_overlap() {
+        let map = PiecewiseMap::new(vec![line(10, 20, 0), line(0, 30, 100), line(25, 40, -5)]);
+        assert_eq!(
+            map.lines(),
+            &[line(0, 10, 100), line(20, 30, 100), line(30, 40, -5)]
+        );
+        assert_eq!(map.get(15), 15);
+        assert_eq!(map.get(25), 125);
+        assert_eq!(map.get(35), 30);
+
+        let input = b"seeds: 15 1 25 1\n\nseed-to-location map:\n15 10 10\n100 0 30\n";
+        let almanac = Almanac::parse(input);
+        assert_eq!(
+            almanac.stages[0].map.lines(),
+            &[line(0, 10, 100), line(10, 20, 5), line(20, 30, 100)]
+        );
+        assert_eq!(day5_part2_composed(input), day5_part2(input));
+    }
+
+    #[test]
+    fn test_composes_maps() {
+        let first = PiecewiseMap::new(vec![line(0, 10, 100), line(20, 30, -10)]);
+        let second = PiecewiseMap::new(vec![line(5, 15, 1), line(100, 105, -100)]);
+        let composed = first.then(&second);
+        for value in -5..40 {
+            assert_eq!(
+                composed.get(value),
+                second.get(first.get(value)),
+                "value {value}"
+            );
+        }
+        assert_eq!(
+            composed.lines(),
+            &[
+                line(5, 10, 100),
+                line(10, 15, 1),
+                line(20, 25, -9),
+                line(25, 30, -10),
+                line(100, 105, -100),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_composed_almanac_matches_stages() {
+        let input = utils::load_example(5);
+        let almanac = Almanac::parse(&input);
+        assert_eq!(almanac.seeds, vec![79, 14, 55, 13]);
+        assert_eq!(almanac.stages.len(), 7);
+        assert_eq!(almanac.stages[0].source, "seed");
+        assert_eq!(almanac.stages[6].destination, "location");
+
+        let map = almanac.compose().unwrap();
+        for seed in 0..120 {
+            let location = almanac
+                .stages
+                .iter()
+                .fold(seed, |value, stage| stage.map.get(value));
+            assert_eq!(map.get(seed), location, "seed {seed}");
+        }
+        assert!(
+            map.to_string().starts_with("0..14 -> 22..36 (+22)\n"),
+            "{map}"
+        );
+    }
+
+    #[test]
+    fn test_preimages_include_unmoved_values() {
+        let map = PiecewiseMap::new(vec![line(0, 10, 20), line(20, 25, -20)]);
+        assert_eq!(map.preimages(0..30).ranges(), &[0..30]);
+        assert_eq!(map.preimages(0..5).ranges(), &[20..25]);
+        assert_eq!(map.preimages(3..4).ranges(), &[23..24]);
+        assert_eq!(map.preimages(22..24).ranges(), &[2..4]);
+        assert_eq!(map.preimages(26..28).ranges(), &[6..8, 26..28]);
+        assert_eq!(map.preimages(12..13).ranges(), &[12..13]);
+    }
+
+    #[test]
+    fn test_inverse_round_trips_sample_seeds() {
+        let input = utils::load_example(5);
+        let almanac = Almanac::parse(&input);
+        let map = almanac.compose().unwrap();
+        for &seed in &almanac.seeds {
+            let location = map.get(seed);
+            assert_eq!(almanac.seed_for_location(location), Ok(Some(seed)));
+            assert_eq!(map.preimages(location..location + 1).min(), Some(seed));
+        }
+        for range in almanac.seed_ranges() {
+            let locations = map.map_range(range.clone()).collect::<IntervalSet>();
+            let seeds = locations
+                .ranges()
+                .iter()
+                .map(|locations| almanac.seeds_for_locations(locations.clone()).unwrap())
+                .fold(IntervalSet::new(), |all, seeds| all.union(&seeds));
+            assert_eq!(
+                seeds
+                    .intersection(&IntervalSet::from(range.clone()))
+                    .ranges(),
+                &[range]
+            );
+        }
+    }
+
+    #[test]
+    fn test_seed_for_unreachable_location_is_none() {
+        let almanac = Almanac {
+            seeds: vec![],
+            stages: vec![Stage {
+                source: "seed".to_owned(),
+                destination: "location".to_owned(),
+                map: PiecewiseMap::new(vec![line(0, 10, 10)]),
+            }],
+        };
+        assert_eq!(almanac.seed_for_location(5), Ok(None));
+        assert_eq!(almanac.seed_for_location(15), Ok(Some(5)));
+        assert_eq!(almanac.seed_for_location(25), Ok(Some(25)));
+    }
+
+    #[test]
+    fn test_converts_between_any_connected_categories() {
+        let input = utils::load_example(5);
+        let almanac = Almanac::parse(&input);
+        assert_eq!(
+            almanac.categories(),
+            vec![
+                "seed",
+                "soil",
+                "fertilizer",
+                "water",
+                "light",
+                "temperature",
+                "humidity",
+                "location"
+            ]
+        );
+        assert_eq!(almanac.convert("seed", "humidity", 79), Ok(78));
+        assert_eq!(almanac.convert("soil", "location", 81), Ok(82));
+        assert_eq!(almanac.convert("water", "water", 5), Ok(5));
+        assert_eq!(almanac.chain("light", "humidity").unwrap().len(), 2);
+
+        let soil_to_location = almanac.map_between("soil", "location").unwrap();
+        let locations = almanac.convert_range("soil", "location", 40..60).unwrap();
+        let expected = (40..60)
+            .map(|soil| soil_to_location.get(soil))
+            .map(|location| location..location + 1)
+            .collect::<IntervalSet>();
+        assert_eq!(locations, expected);
+        assert_eq!(
+            almanac.unconvert_range("soil", "location", 82..83),
+            Ok(IntervalSet::from(81..82))
+        );
+    }
+
+    #[test]
+    fn test_reports_broken_and_ambiguous_chains() {
+        let input = utils::load_example(5);
+        let mut almanac = Almanac::parse(&input);
+        assert_eq!(
+            almanac.convert("seed", "dirt", 1),
+            Err(ChainError::UnknownCategory("dirt".to_owned()))
+        );
+        assert_eq!(
+            almanac.convert("location", "seed", 1),
+            Err(ChainError::Broken {
+                from: "location".to_owned(),
+                to: "seed".to_owned(),
+            })
+        );
+
+        almanac.stages.push(Stage {
+            source: "seed".to_owned(),
+            destination: "water".to_owned(),
+            map: PiecewiseMap::default(),
+        });
+        assert_eq!(
+            almanac.compose(),
+            Err(ChainError::Ambiguous {
+                from: "seed".to_owned(),
+                to: "location".to_owned(),
+            })
+        );
+        assert_eq!(almanac.convert("light", "location", 74), Ok(82));
+
+        almanac.stages.retain(|stage| stage.source != "light");
+        assert_eq!(
+            almanac.compose().unwrap_err().to_string(),
+            "no chain of maps leads from seed to location"
+        );
+    }
+
+    #[test]
+    fn test_validates_sample_almanac() {
+        let input = utils::load_example(5);
+        assert_eq!(validate(&input), vec![]);
+    }
+
+    #[test]
+    fn test_reports_overlapping_and_colliding_lines() {
+        let input = b"seeds: 1 2\n\nseed-to-soil map:\n50 98 2\n10 90 10\n10 200 5\nten 1 1\n\nsoil-to-fertilizer map:\n0 0 10\n0 20 10\n";
+        let issues = validate(input)
+            .into_iter()
+            .map(|issue| issue.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues,
+            vec![
+                "3: seed-to-soil: 100..200 is not mapped",
+                "5: seed-to-soil: source 98..100 overlaps line 4",
+                "6: seed-to-soil: destination 10..15 collides with line 5",
+                "7: seed-to-soil: expected `<destination> <source> <length>`",
+                "9: soil-to-fertilizer: 10..20 is not mapped",
+                "11: soil-to-fertilizer: destination 0..10 collides with line 10",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_day5_composed_example() {
+        let input = utils::load_example(5);
+        assert_eq!(day5_part1_composed(&input), 35);
+        assert_eq!(day5_part2_composed(&input), 46);
+    }
+
+    #[test]
+    fn test_day5_composed_real() {
+        let input = utils::load_real(5);
+        assert_eq!(day5_part1_composed(&input), day5_part1(&input));
+        assert_eq!(day5_part2_composed(&input), day5_part2(&input));
+    }
+
+    #[test]
+    fn test_finds_line_containing_value() {
+        let lines = [line(0, 10, 5), line(10, 12, -3), line(20, 30, 1)];
+        assert_eq!(line_containing(&lines, -1), None);
+        assert_eq!(line_containing(&lines, 0), Some(&lines[0]));
+        assert_eq!(line_containing(&lines, 10), Some(&lines[1]));
+        assert_eq!(line_containing(&lines, 12), None);
+        assert_eq!(line_containing(&lines, 29), Some(&lines[2]));
+        assert_eq!(line_containing(&lines, 30), None);
+    }
+
+    #[test]
+    fn test_parses_stage_sorted_by_source() {
+        let input = b"50 98 2\n52 50 48\n\nsoil-to-fertilizer map:\n";
+        let mut lines = vec![line(0, 1, 1)];
+        assert_eq!(parse_stage(input, &mut lines), 17);
+        assert_eq!(lines, vec![line(98, 100, -48), line(50, 98, 2)]);
+        sort_stage(input, &mut lines);
+        assert_eq!(lines, vec![line(50, 98, 2), line(98, 100, -48)]);
+    }
+
+    #[test]
+    fn test_sorted_stage_keeps_the_earlier_of_overlapping_lines() {
+        let input = b"100 5 10\n50 0 10\n";
+        let mut lines = Vec::new();
+        parse_stage(input, &mut lines);
+        sort_stage(input, &mut lines);
+        assert_eq!(lines, vec![line(0, 5, 50), line(5, 15, 95)]);
+
+        let few = format!("seeds: 7\n\nseed-to-soil map:\n{}", "100 5 10\n50 0 10\n");
+        let many = format!(
+            "seeds:{}\n\nseed-to-soil map:\n{}",
+            " 7".repeat(SORTED_STAGE_SEEDS),
+            "100 5 10\n50 0 10\n"
+        );
+        assert_eq!(day5_part1(few.as_bytes()), 102);
+        assert_eq!(day5_part1(many.as_bytes()), 102);
+        assert_eq!(day5_part1_sorted(few.as_bytes()), 102);
+    }
+
     #[test]
     fn test_day5_part1_example() {
         let input = utils::load_example(5);
@@ -339,6 +1469,7 @@ pub mod tests {
     fn test_day5_part1_real() {
         let input = utils::load_real(5);
         assert_eq!(day5_part1(&input), 227653707);
+        assert_eq!(day5_part1_sorted(&input), 227653707);
     }
 
     #[test]
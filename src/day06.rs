@@ -1,131 +1,258 @@
+use std::fmt::{self, Display};
+
 pub fn day6_part1(input: &[u8]) -> u64 {
-    entries(input)
+    races(input).map(ways_to_win).product()
+}
+
+pub fn day6_part2(input: &[u8]) -> u64 {
+    ways_to_win(entry(input).expect("malformed races"))
+}
+
+/// `day6_part1` solving the quadratic in `f32`, which can be off by one once times or distances
+/// pass about 2^24.
+pub fn day6_part1_fast(input: &[u8]) -> u64 {
+    races(input)
         .map(evaluate_min_maxes)
         .map(|(min, max)| 1 + ((max).floor() as u64) - ((min).ceil() as u64))
         .product()
 }
 
-pub fn day6_part2(input: &[u8]) -> u64 {
-    let entry = entry(input);
+/// `day6_part2` solving the quadratic in `f32`. See [`day6_part1_fast`].
+pub fn day6_part2_fast(input: &[u8]) -> u64 {
+    let entry = entry(input).expect("malformed races");
     let (min, max) = evaluate_min_maxes(entry);
 
     1 + ((max).floor() as u64) - ((min).ceil() as u64)
 }
 
 fn evaluate_min_maxes(entry: Entry) -> (f32, f32) {
-    let rooted = (entry.time.powf(2.0) - 4.0 * (entry.distance + 1.0)).sqrt();
-    let min = (entry.time - rooted) / 2.0;
-    let max = (entry.time + rooted) / 2.0;
+    let (time, distance) = (entry.time as f32, entry.distance as f32);
+    let rooted = (time.powf(2.0) - 4.0 * (distance + 1.0)).sqrt();
+    let min = (time - rooted) / 2.0;
+    let max = (time + rooted) / 2.0;
     (min, max)
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-struct Entry {
-    time: f32,
-    distance: f32,
+/// Counts the hold times `h` that beat the record, i.e. `h * (time - h) > distance`, exactly.
+fn ways_to_win(entry: Entry) -> u64 {
+    let time = u128::from(entry.time);
+    let distance = u128::from(entry.distance);
+    let wins = |hold: u128| hold * (time - hold) > distance;
+    if !wins(time / 2) {
+        return 0;
+    }
+
+    // The roots of h^2 - time * h + distance are (time +- sqrt(time^2 - 4 * distance)) / 2. The
+    // integer square root puts the first winning hold time within a step of `min`.
+    let mut min = (time - (time * time - 4 * distance).isqrt()) / 2;
+    while min > 0 && wins(min - 1) {
+        min -= 1;
+    }
+    while !wins(min) {
+        min += 1;
+    }
+
+    // Winning hold times are symmetric around time / 2.
+    (time - 2 * min + 1) as u64
 }
 
-struct EntryIter<'a> {
-    input: &'a [u8],
-    line2_start: usize,
-    pos: usize,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub time: u64,
+    pub distance: u64,
 }
 
-impl Iterator for EntryIter<'_> {
-    type Item = Entry;
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RaceError {
+    /// Line `line` (starting at 1) is missing or doesn't start with `header`.
+    MissingHeader {
+        line: usize,
+        header: &'static str,
+    },
+    InvalidNumber {
+        line: usize,
+        token: String,
+    },
+    /// The number doesn't fit in a `u64` once its digits are joined up.
+    TooLarge {
+        line: usize,
+    },
+    CountMismatch {
+        times: usize,
+        distances: usize,
+    },
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.pos + self.line2_start >= self.input.len() {
-            return None;
+impl Display for RaceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RaceError::MissingHeader { line, header } => {
+                write!(f, "line {line}: expected `{header}`")
+            }
+            RaceError::InvalidNumber { line, token } => {
+                write!(f, "line {line}: {token:?} is not a number")
+            }
+            RaceError::TooLarge { line } => write!(f, "line {line}: number is too large"),
+            RaceError::CountMismatch { times, distances } => {
+                write!(f, "{times} times but {distances} distances")
+            }
         }
+    }
+}
+
+impl std::error::Error for RaceError {}
 
-        let (time, bytes_read_l1) = parse_number(&self.input[self.pos..]);
-        let (distance, bytes_read_l2) = parse_number(&self.input[self.line2_start + self.pos..]);
+/// The whitespace-separated tokens of a line.
+#[derive(Clone)]
+struct Tokens<'a>(&'a [u8]);
 
-        debug_assert_eq!(bytes_read_l1, bytes_read_l2);
-        self.pos += bytes_read_l1;
+impl<'a> Iterator for Tokens<'a> {
+    type Item = &'a [u8];
 
-        Some(Entry { time, distance })
+    fn next(&mut self) -> Option<&'a [u8]> {
+        let start = self.0.iter().position(|byte| !byte.is_ascii_whitespace())?;
+        let rest = &self.0[start..];
+        let end = rest
+            .iter()
+            .position(u8::is_ascii_whitespace)
+            .unwrap_or(rest.len());
+        self.0 = &rest[end..];
+        Some(&rest[..end])
     }
 }
 
-fn entry(input: &[u8]) -> Entry {
-    let (time, bytes_read_l1) = parse_number_with_spaces(&input[9..]);
-    let (distance, bytes_read_l2) = parse_number_with_spaces(&input[19 + bytes_read_l1..]);
+/// The tokens after `header` on the next line of `input`, the `line`th (starting at 1).
+fn tokens<'a>(
+    input: &mut &'a [u8],
+    line: usize,
+    header: &'static str,
+) -> Result<Tokens<'a>, RaceError> {
+    let end = memchr::memchr(b'\n', input).unwrap_or(input.len());
+    let text = &input[..end];
+    *input = input.get(end + 1..).unwrap_or_default();
+    text.strip_prefix(header.as_bytes())
+        .map(Tokens)
+        .ok_or(RaceError::MissingHeader { line, header })
+}
 
-    debug_assert_eq!(bytes_read_l1, bytes_read_l2);
+/// Appends the digits of `token` to `number`.
+fn push_digits(number: u64, token: &[u8], line: usize) -> Result<u64, RaceError> {
+    token.iter().try_fold(number, |number, &digit| {
+        if !digit.is_ascii_digit() {
+            return Err(RaceError::InvalidNumber {
+                line,
+                token: String::from_utf8_lossy(token).into_owned(),
+            });
+        }
+        number
+            .checked_mul(10)
+            .and_then(|number| number.checked_add(u64::from(digit - b'0')))
+            .ok_or(RaceError::TooLarge { line })
+    })
+}
 
-    Entry { time, distance }
+/// The races read by [`entries`]. Stops after the first error.
+#[derive(Clone)]
+pub struct Entries<'a> {
+    times: Tokens<'a>,
+    distances: Tokens<'a>,
+    read: usize,
+    failed: bool,
 }
 
-fn entries(input: &[u8]) -> EntryIter {
-    let line2_start = memchr::memchr(b'\n', input).unwrap() + 1;
-    EntryIter {
-        input,
-        line2_start,
-        pos: 9, // ignore header text
+impl Iterator for Entries<'_> {
+    type Item = Result<Entry, RaceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let entry = match (self.times.next(), self.distances.next()) {
+            (None, None) => return None,
+            (Some(time), Some(distance)) => push_digits(0, time, 1).and_then(|time| {
+                Ok(Entry {
+                    time,
+                    distance: push_digits(0, distance, 2)?,
+                })
+            }),
+            (time, distance) => Err(RaceError::CountMismatch {
+                times: self.read + usize::from(time.is_some()) + self.times.clone().count(),
+                distances: self.read
+                    + usize::from(distance.is_some())
+                    + self.distances.clone().count(),
+            }),
+        };
+        self.read += 1;
+        self.failed = entry.is_err();
+        Some(entry)
     }
 }
 
-fn parse_number_with_spaces(input: &[u8]) -> (f32, usize) {
-    let mut number = 0.0;
-    let mut i = 0;
-    while input[i] == b' ' {
-        i += 1;
-    }
-    while input[i] != b'\n' {
-        if input[i] == b' ' {
-            i += 1;
-            continue;
-        }
+/// [`entries`] for the solvers, which panic on malformed input rather than skip races.
+fn races(input: &[u8]) -> impl Iterator<Item = Entry> + '_ {
+    entries(input)
+        .expect("malformed races")
+        .map(|entry| entry.expect("malformed race"))
+}
 
-        number = number * 10.0 + f32::from(input[i] - b'0');
-        i += 1;
-    }
-    (number, i)
+/// Reads each column of the two lines as its own race. The columns can be spaced any way.
+pub fn entries(mut input: &[u8]) -> Result<Entries<'_>, RaceError> {
+    Ok(Entries {
+        times: tokens(&mut input, 1, "Time:")?,
+        distances: tokens(&mut input, 2, "Distance:")?,
+        read: 0,
+        failed: false,
+    })
 }
 
-fn parse_number(input: &[u8]) -> (f32, usize) {
-    let mut number = 0.0;
-    let mut i = 0;
-    while input[i] == b' ' {
-        i += 1;
-    }
-    while input[i] != b' ' && input[i] != b'\n' {
-        number = number * 10.0 + f32::from(input[i] - b'0');
-        i += 1;
+/// Reads each line as a single race, ignoring the spaces between digits.
+pub fn entry(mut input: &[u8]) -> Result<Entry, RaceError> {
+    // Joins up the tokens of a line, counting them as it goes.
+    let join = |mut tokens: Tokens<'_>, line| {
+        tokens.try_fold((0, 0), |(number, count), token| {
+            Ok((push_digits(number, token, line)?, count + 1))
+        })
+    };
+    let (time, times) = join(tokens(&mut input, 1, "Time:")?, 1)?;
+    let (distance, distances) = join(tokens(&mut input, 2, "Distance:")?, 2)?;
+    if times != distances {
+        return Err(RaceError::CountMismatch { times, distances });
     }
-    (number, i + 1)
+    Ok(Entry { time, distance })
 }
 
 #[cfg(test)]
 pub mod tests {
     use super::*;
-    use crate::utils;
+    use crate::utils::{self, XorShift};
+
+    fn collect_entries(input: &[u8]) -> Result<Vec<Entry>, RaceError> {
+        entries(input)?.collect()
+    }
 
     #[test]
     fn test_finds_numbers() {
         let input = "Time:      7  15   30\nDistance:  9  40  200\n";
-        let mut iter = entries(input.as_bytes());
+        let mut iter = collect_entries(input.as_bytes()).unwrap().into_iter();
         assert_eq!(
             iter.next(),
             Some(Entry {
-                time: 7.0,
-                distance: 9.0
+                time: 7,
+                distance: 9
             })
         );
         assert_eq!(
             iter.next(),
             Some(Entry {
-                time: 15.0,
-                distance: 40.0
+                time: 15,
+                distance: 40
             })
         );
         assert_eq!(
             iter.next(),
             Some(Entry {
-                time: 30.0,
-                distance: 200.0
+                time: 30,
+                distance: 200
             })
         );
         assert_eq!(iter.next(), None);
@@ -134,16 +261,145 @@ pub mod tests {
     #[test]
     fn test_finds_numbers_with_spaces() {
         let input = "Time:      7  15   30\nDistance:  9  40  200\n";
-        let entry = entry(input.as_bytes());
+        let entry = entry(input.as_bytes()).unwrap();
         assert_eq!(
             entry,
             Entry {
-                time: 71530.0,
-                distance: 940200.0
+                time: 71530,
+                distance: 940200
             }
         );
     }
 
+    #[test]
+    fn test_finds_numbers_with_any_alignment() {
+        let input = b"Time: 7 15    30\r\nDistance:      9 40 200";
+        let expected = vec![
+            Entry {
+                time: 7,
+                distance: 9,
+            },
+            Entry {
+                time: 15,
+                distance: 40,
+            },
+            Entry {
+                time: 30,
+                distance: 200,
+            },
+        ];
+        assert_eq!(collect_entries(input), Ok(expected));
+        assert_eq!(
+            entry(input),
+            Ok(Entry {
+                time: 71530,
+                distance: 940200
+            })
+        );
+    }
+
+    #[test]
+    fn test_reports_malformed_races() {
+        assert_eq!(
+            collect_entries(b"Time: 7 15\nDistance: 9 40 200\n"),
+            Err(RaceError::CountMismatch {
+                times: 2,
+                distances: 3
+            })
+        );
+        assert_eq!(
+            entry(b"Time: 7 15 30\nDistance: 9 40\n"),
+            Err(RaceError::CountMismatch {
+                times: 3,
+                distances: 2
+            })
+        );
+        let mut partial = entries(b"Time: 7 15\nDistance: 9\n").unwrap();
+        assert_eq!(
+            partial.next(),
+            Some(Ok(Entry {
+                time: 7,
+                distance: 9
+            }))
+        );
+        assert_eq!(
+            partial.next(),
+            Some(Err(RaceError::CountMismatch {
+                times: 2,
+                distances: 1
+            }))
+        );
+        assert_eq!(partial.next(), None);
+        assert_eq!(
+            collect_entries(b"Time: 7\nDist: 9\n"),
+            Err(RaceError::MissingHeader {
+                line: 2,
+                header: "Distance:"
+            })
+        );
+        assert_eq!(
+            collect_entries(b"Time: 7 1x\nDistance: 9 4\n")
+                .unwrap_err()
+                .to_string(),
+            "line 1: \"1x\" is not a number"
+        );
+        assert_eq!(
+            entry(b"Time: 99999999999 99999999999\nDistance: 1 2\n"),
+            Err(RaceError::TooLarge { line: 1 })
+        );
+    }
+
+    fn ways(time: u64, distance: u64) -> u64 {
+        ways_to_win(Entry { time, distance })
+    }
+
+    #[test]
+    fn test_counts_ways_at_exact_ties() {
+        // Holding for 10 or 20 exactly ties the record, which doesn't win.
+        assert_eq!(ways(30, 200), 9);
+        assert_eq!(ways(30, 199), 11);
+        assert_eq!(ways(4, 4), 0);
+        assert_eq!(ways(4, 3), 1);
+        assert_eq!(ways(5, 6), 0);
+        assert_eq!(ways(5, 5), 2);
+        assert_eq!(ways(0, 0), 0);
+        assert_eq!(ways(1, 0), 0);
+        assert_eq!(ways(2, 0), 1);
+        assert_eq!(ways(10, 1000), 0);
+    }
+
+    #[test]
+    fn test_counts_ways_for_large_races() {
+        let mut rng = XorShift::new(0x853c_49e6_748f_ea9b);
+        for _ in 0..10_000 {
+            // Keeps hold * (time - hold) within a u64.
+            let time = rng.below(1 << 33) + 2;
+            let hold = rng.below(time / 2) + 1;
+            let record = hold * (time - hold);
+            assert_eq!(ways(time, record), time - 2 * hold - 1, "{time} {record}");
+            assert_eq!(
+                ways(time, record - 1),
+                time - 2 * hold + 1,
+                "{time} {record}"
+            );
+        }
+
+        assert_eq!(ways(u64::MAX, u64::MAX), u64::MAX - 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "CountMismatch")]
+    fn test_solvers_reject_mismatched_columns() {
+        day6_part1(b"Time: 7 15 30\nDistance: 9 40\n");
+    }
+
+    #[test]
+    fn test_fast_mode_drifts_on_large_races() {
+        let input = b"Time:            100000007\nDistance: 2499999850000000\n";
+        assert_eq!(day6_part2(input), ways(100_000_007, 2_499_999_850_000_000));
+        assert_ne!(day6_part2_fast(input), day6_part2(input));
+    }
+
     #[test]
     fn test_day6_part1_example() {
         let input = utils::load_example(6);
@@ -167,4 +423,11 @@ pub mod tests {
         let input = utils::load_real(6);
         assert_eq!(day6_part2(&input), 46561107);
     }
+
+    #[test]
+    fn test_day6_fast_real() {
+        let input = utils::load_real(6);
+        assert_eq!(day6_part1_fast(&input), 160816);
+        assert_eq!(day6_part2_fast(&input), 46561107);
+    }
 }
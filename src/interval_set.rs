@@ -0,0 +1,237 @@
+use std::ops::{Add, Range};
+
+/// A set of values stored as sorted, non-overlapping half-open ranges. Ranges that overlap or
+/// touch (e.g. `10..50` and `50..100`) are always fused into a single range.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntervalSet<T = i64> {
+    ranges: Vec<Range<T>>,
+}
+
+impl<T> Default for IntervalSet<T> {
+    fn default() -> Self {
+        Self { ranges: Vec::new() }
+    }
+}
+
+impl<T: Copy + Ord> IntervalSet<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn ranges(&self) -> &[Range<T>] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+    }
+
+    pub fn min(&self) -> Option<T> {
+        self.ranges.first().map(|range| range.start)
+    }
+
+    pub fn max(&self) -> Option<T> {
+        self.ranges.last().map(|range| range.end)
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        let index = self.ranges.partition_point(|range| range.end <= value);
+        self.ranges
+            .get(index)
+            .is_some_and(|range| range.start <= value)
+    }
+
+    /// The index of the first range that ends at or after `value`, i.e. the first range that
+    /// could overlap or touch a range starting at `value`.
+    fn first_touching(&self, value: T) -> usize {
+        self.ranges.partition_point(|range| range.end < value)
+    }
+
+    pub fn insert(&mut self, range: Range<T>) {
+        if range.is_empty() {
+            return;
+        }
+        let first = self.first_touching(range.start);
+        let last = first + self.ranges[first..].partition_point(|r| r.start <= range.end);
+        let merged = if first == last {
+            range
+        } else {
+            self.ranges[first].start.min(range.start)..self.ranges[last - 1].end.max(range.end)
+        };
+        self.ranges.splice(first..last, [merged]);
+    }
+
+    pub fn remove(&mut self, range: Range<T>) {
+        if range.is_empty() {
+            return;
+        }
+        let first = self.ranges.partition_point(|r| r.end <= range.start);
+        let last = first + self.ranges[first..].partition_point(|r| r.start < range.end);
+        if first == last {
+            return;
+        }
+        let before = self.ranges[first].start..range.start;
+        let after = range.end..self.ranges[last - 1].end;
+        let kept = [before, after].into_iter().filter(|r| !r.is_empty());
+        self.ranges.splice(first..last, kept);
+    }
+
+    /// The parts of the set that fall inside `range`.
+    pub fn overlapping(&self, range: Range<T>) -> impl Iterator<Item = Range<T>> + '_ {
+        let first = self.ranges.partition_point(|r| r.end <= range.start);
+        self.ranges[first..]
+            .iter()
+            .take_while(move |r| r.start < range.end)
+            .map(move |r| r.start.max(range.start)..r.end.min(range.end))
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut ranges = self
+            .ranges
+            .iter()
+            .chain(&other.ranges)
+            .cloned()
+            .collect::<Vec<_>>();
+        ranges.sort_unstable_by_key(|range| range.start);
+        let mut set = Self::new();
+        for range in ranges {
+            match set.ranges.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => set.ranges.push(range),
+            }
+        }
+        set
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut set = Self::new();
+        let (mut left, mut right) = (0, 0);
+        while left < self.ranges.len() && right < other.ranges.len() {
+            let (a, b) = (&self.ranges[left], &other.ranges[right]);
+            let overlap = a.start.max(b.start)..a.end.min(b.end);
+            if !overlap.is_empty() {
+                set.ranges.push(overlap);
+            }
+            if a.end < b.end {
+                left += 1;
+            } else {
+                right += 1;
+            }
+        }
+        set
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut set = self.clone();
+        for range in &other.ranges {
+            set.remove(range.clone());
+        }
+        set
+    }
+
+    /// Moves every range in the set by `offset`.
+    pub fn shift(&self, offset: T) -> Self
+    where
+        T: Add<Output = T>,
+    {
+        Self {
+            ranges: self
+                .ranges
+                .iter()
+                .map(|range| range.start + offset..range.end + offset)
+                .collect(),
+        }
+    }
+}
+
+impl<T: Copy + Ord> From<Range<T>> for IntervalSet<T> {
+    fn from(range: Range<T>) -> Self {
+        let mut set = Self::new();
+        set.insert(range);
+        set
+    }
+}
+
+impl<T: Copy + Ord> FromIterator<Range<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Range<T>>>(iter: I) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<T: Copy + Ord> Extend<Range<T>> for IntervalSet<T> {
+    fn extend<I: IntoIterator<Item = Range<T>>>(&mut self, iter: I) {
+        for range in iter {
+            self.insert(range);
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    #![allow(clippy::single_range_in_vec_init)]
+
+    use super::*;
+
+    fn set(ranges: &[Range<i64>]) -> IntervalSet {
+        ranges.iter().cloned().collect()
+    }
+
+    #[test]
+    fn inserting_keeps_ranges_sorted_and_fused() {
+        assert_eq!(set(&[50..100, 10..20]).ranges(), &[10..20, 50..100]);
+        assert_eq!(set(&[10..50, 50..100]).ranges(), &[10..100]);
+        assert_eq!(set(&[10..20, 30..40, 15..35]).ranges(), &[10..40]);
+        assert_eq!(set(&[10..20, 30..40, 0..100]).ranges(), &[0..100]);
+        assert_eq!(set(&[10..20, 12..14]).ranges(), &[10..20]);
+        assert_eq!(set(&[10..20, 5..5, 20..20]).ranges(), &[10..20]);
+        assert_eq!(set(&[30..40, 10..20, 20..25]).ranges(), &[10..25, 30..40]);
+    }
+
+    #[test]
+    fn removing_splits_ranges() {
+        let mut ranges = set(&[0..10, 20..30]);
+        ranges.remove(5..25);
+        assert_eq!(ranges.ranges(), &[0..5, 25..30]);
+        ranges.remove(26..28);
+        assert_eq!(ranges.ranges(), &[0..5, 25..26, 28..30]);
+        ranges.remove(-10..100);
+        assert!(ranges.is_empty());
+
+        let mut ranges = set(&[0..10]);
+        ranges.remove(10..20);
+        assert_eq!(ranges.ranges(), &[0..10]);
+    }
+
+    #[test]
+    fn contains_checks_half_open_ranges() {
+        let ranges = set(&[0..10, 20..30]);
+        assert!(ranges.contains(0));
+        assert!(ranges.contains(9));
+        assert!(!ranges.contains(10));
+        assert!(ranges.contains(20));
+        assert!(!ranges.contains(-1));
+        assert!(!ranges.contains(30));
+    }
+
+    #[test]
+    fn set_operations() {
+        let a = set(&[0..10, 20..30]);
+        let b = set(&[5..25, 40..50]);
+        assert_eq!(a.union(&b).ranges(), &[0..30, 40..50]);
+        assert_eq!(a.intersection(&b).ranges(), &[5..10, 20..25]);
+        assert_eq!(a.difference(&b).ranges(), &[0..5, 25..30]);
+        assert_eq!(b.difference(&a).ranges(), &[10..20, 40..50]);
+        assert_eq!(a.shift(-5).ranges(), &[-5..5, 15..25]);
+        assert_eq!(
+            a.overlapping(5..22).collect::<Vec<_>>(),
+            vec![5..10, 20..22]
+        );
+        assert_eq!((a.min(), a.max()), (Some(0), Some(30)));
+    }
+}
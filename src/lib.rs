@@ -2,6 +2,7 @@
 #![feature(iter_map_windows)]
 #![feature(iter_collect_into)]
 
+pub mod interval_set;
 pub mod utils;
 
 pub mod day01;
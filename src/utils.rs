@@ -11,3 +11,21 @@ pub fn load_example_with_suffix(day: usize, suffix: &str) -> Vec<u8> {
 pub fn load_real(day: usize) -> Vec<u8> {
     fs::read(format!("inputs/day{day}.txt")).unwrap()
 }
+
+/// A small xorshift generator, so randomised tests and benches are repeatable.
+pub struct XorShift(u64);
+
+impl XorShift {
+    /// `seed` must be non-zero.
+    pub fn new(seed: u64) -> Self {
+        XorShift(seed)
+    }
+
+    /// The next number in `0..limit`.
+    pub fn below(&mut self, limit: u64) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 % limit
+    }
+}